@@ -0,0 +1,14 @@
+//! Decode arbitrary bytes as ALZ-modified bzip2 data.
+
+#![no_main]
+
+use std::io::Read;
+
+use libfuzzer_sys::fuzz_target;
+use unalz_rs::decompress::bzip2::AlzBzip2Decoder;
+
+fuzz_target!(|data: &[u8]| {
+    let mut decoder = AlzBzip2Decoder::new(data.to_vec());
+    // Bound the output: a tiny input can describe ~900 KB per block.
+    let _ = decoder.by_ref().take(64 << 20).read_to_end(&mut Vec::new());
+});
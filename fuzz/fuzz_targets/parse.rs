@@ -0,0 +1,22 @@
+//! Parse arbitrary bytes as an archive and extract every entry in memory.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use unalz_rs::archive::AlzArchive;
+use unalz_rs::extract::{ExtractOptions, extract_all_to};
+use unalz_rs::sink::MemorySink;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(mut archive) = AlzArchive::from_bytes(data.to_vec()) else {
+        return;
+    };
+    let mut sink = MemorySink::new();
+    let opts = ExtractOptions {
+        password: Some("password"),
+        quiet: true,
+        keep_going: true,
+        ..Default::default()
+    };
+    let _ = extract_all_to(&mut archive, &mut sink, &opts);
+});
@@ -1,9 +1,15 @@
-use std::io::{Read, Seek, SeekFrom};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
 
 use crate::crypto::ENCR_HEADER_LEN;
-use crate::encoding::cp949_to_utf8;
+use crate::encoding::NameDecoding;
+#[cfg(feature = "normalize")]
+use crate::encoding::NameNormalization;
 use crate::error::{AlzError, AlzResult};
+use crate::extract::{ExtractOptions, ExtractSummary};
+use crate::format::{self, ArchiveFormat};
 use crate::multivolume::MultiVolumeReader;
+use crate::sink::ExtractSink;
 
 // ALZ signatures (little-endian u32)
 const SIG_ALZ_FILE_HEADER: u32 = 0x015a4c41; // "ALZ\x01"
@@ -16,6 +22,8 @@ const SIG_SPLIT_MARKER: u32 = 0x035a4c43; // "CLZ\x03"
 // File descriptor flags
 const DESC_ENCRYPTED: u8 = 0x01;
 const DESC_DATA_DESCR: u8 = 0x08;
+const DESC_SIZE_MASK: u8 = 0xF0;
+const DESC_KNOWN: u8 = DESC_ENCRYPTED | DESC_DATA_DESCR | DESC_SIZE_MASK;
 
 // File attributes
 pub const ATTR_READONLY: u8 = 0x01;
@@ -34,7 +42,8 @@ pub enum CompressionMethod {
 }
 
 impl CompressionMethod {
-    fn from_byte(b: u8) -> Self {
+    /// Decode a method byte from a local file header.
+    pub fn from_byte(b: u8) -> Self {
         match b {
             0 => Self::Store,
             1 => Self::Bzip2,
@@ -42,15 +51,29 @@ impl CompressionMethod {
             n => Self::Unknown(n),
         }
     }
+
+    /// The method byte as stored in the local file header; the inverse of
+    /// [`from_byte`](Self::from_byte). (`Unknown(n)` for a known `n` is
+    /// never produced by parsing and maps back to the known method.)
+    #[doc(alias = "method_code")]
+    pub fn as_byte(self) -> u8 {
+        match self {
+            Self::Store => 0,
+            Self::Bzip2 => 1,
+            Self::Deflate => 2,
+            Self::Unknown(n) => n,
+        }
+    }
 }
 
 impl std::fmt::Display for CompressionMethod {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // `pad` so listings can align the column with `{:<7}`.
         match self {
-            Self::Store => write!(f, "Store"),
-            Self::Bzip2 => write!(f, "BZip2"),
-            Self::Deflate => write!(f, "Deflate"),
-            Self::Unknown(n) => write!(f, "Unknown({n})"),
+            Self::Store => f.pad("Store"),
+            Self::Bzip2 => f.pad("BZip2"),
+            Self::Deflate => f.pad("Deflate"),
+            Self::Unknown(n) => f.pad(&format!("Unknown({n})")),
         }
     }
 }
@@ -67,6 +90,14 @@ pub struct AlzFileEntry {
     pub uncompressed_size: u64,
     pub data_pos: u64,
     pub enc_check: Option<[u8; ENCR_HEADER_LEN]>,
+    /// Header bytes of unknown meaning, kept for research: the byte after
+    /// the file descriptor and the one after the compression method (0 when
+    /// the header has no size fields).
+    pub raw_reserved: [u8; 2],
+    /// The header has no size fields, yet data follows. `compressed_size`
+    /// is then the distance to the next record signature, and the method
+    /// and CRC are unknown, so the entry cannot be extracted.
+    pub size_unknown: bool,
 }
 
 impl AlzFileEntry {
@@ -85,210 +116,1012 @@ impl AlzFileEntry {
     pub fn has_data_descriptor(&self) -> bool {
         self.file_descriptor & DESC_DATA_DESCR != 0
     }
+
+    /// The file name with `\` separators turned into `/`.
+    pub fn normalized_name(&self) -> String {
+        self.file_name.replace('\\', "/")
+    }
+
+    /// The non-empty components of the file name, split at `/` and `\`.
+    pub fn components(&self) -> impl Iterator<Item = &str> {
+        self.file_name.split(['/', '\\']).filter(|c| !c.is_empty())
+    }
+
+    /// Whether the header carries a CRC to check. Some archivers store 0
+    /// for Store entries rather than computing one; a zero CRC on a
+    /// non-empty Store entry is taken to mean that.
+    pub fn has_crc(&self) -> bool {
+        !(self.file_crc == 0
+            && self.compression_method == CompressionMethod::Store
+            && self.uncompressed_size > 0)
+    }
+
+    /// Check `data`, e.g. a previously extracted copy, against this entry's
+    /// size and CRC without touching the archive.
+    pub fn verify_data<R: Read>(&self, mut data: R) -> io::Result<bool> {
+        let mut hasher = crc32fast::Hasher::new();
+        let mut buf = vec![0u8; crate::decompress::DEFAULT_BUF_SIZE];
+        let mut size = 0u64;
+        loop {
+            let n = match data.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            hasher.update(&buf[..n]);
+            size += n as u64;
+        }
+        Ok(size == self.uncompressed_size
+            && (!self.has_crc() || hasher.finalize() == self.file_crc))
+    }
+
+    /// [`verify_data`](Self::verify_data) for a file on disk.
+    pub fn verify_file<P: AsRef<Path>>(&self, path: P) -> io::Result<bool> {
+        let file = std::fs::File::open(path)?;
+        if file.metadata()?.len() != self.uncompressed_size {
+            return Ok(false);
+        }
+        self.verify_data(file)
+    }
+}
+
+/// A suitable [`ParseOptions::sfx_scan_limit`]: self-extracting stubs are
+/// well under this size.
+pub const DEFAULT_SFX_SCAN_LIMIT: u64 = 4 << 20;
+
+/// Settings for parsing an archive's headers.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ParseOptions {
+    /// How entry names are decoded.
+    pub name_decoding: NameDecoding,
+    /// Fail with [`AlzError::MissingEndRecord`] when the input runs out
+    /// before the end-of-central-directory record instead of accepting the
+    /// entries read so far; see [`AlzArchive::end_record_seen`].
+    pub require_end_record: bool,
+    /// When the input does not start with the archive signature, look for
+    /// it within this many bytes, as in a self-extracting executable where
+    /// the archive follows a program stub. Offsets are then relative to the
+    /// signature. `None` requires the signature at the start.
+    pub sfx_scan_limit: Option<u64>,
+    /// Unicode normalization applied to names after decoding.
+    #[cfg(feature = "normalize")]
+    pub normalization: NameNormalization,
 }
 
-pub struct AlzArchive {
-    pub reader: MultiVolumeReader,
+pub struct AlzArchive<'a> {
+    pub reader: MultiVolumeReader<'a>,
     pub entries: Vec<AlzFileEntry>,
     pub is_encrypted: bool,
     pub is_data_descr: bool,
+    /// Number of split markers (`CLZ\x03`) seen while parsing.
+    pub split_markers: u32,
+    /// Whether parsing reached the end-of-central-directory record
+    /// (`CLZ\x02`) rather than running out of input.
+    pub end_record_seen: bool,
+    /// Normalization applied to entry names, and by
+    /// [`contains`](Self::contains) to the name looked up.
+    #[cfg(feature = "normalize")]
+    pub normalization: NameNormalization,
 }
 
-impl AlzArchive {
+impl<'a> AlzArchive<'a> {
     pub fn open(path: &str) -> AlzResult<Self> {
         let reader = MultiVolumeReader::open(path)?;
-        let mut archive = AlzArchive {
-            reader,
-            entries: Vec::new(),
-            is_encrypted: false,
-            is_data_descr: false,
-        };
-        archive.parse()?;
-        Ok(archive)
+        Self::from_reader(reader)
     }
 
     pub fn from_bytes(data: Vec<u8>) -> AlzResult<Self> {
         let reader = MultiVolumeReader::from_bytes(data);
-        let mut archive = AlzArchive {
+        Self::from_reader(reader)
+    }
+
+    /// Parse an archive held in borrowed memory without copying it.
+    pub fn from_slice(data: &'a [u8]) -> AlzResult<Self> {
+        Self::from_reader(MultiVolumeReader::from_slice(data))
+    }
+
+    /// Open an archive for incremental listing.
+    ///
+    /// Headers are parsed lazily as the returned iterator is advanced, so
+    /// huge archives can be inspected without building the full entry list.
+    pub fn open_streaming(path: &str) -> AlzResult<EntryStream> {
+        Self::open_streaming_with(path, &ParseOptions::default())
+    }
+
+    /// [`open_streaming`](Self::open_streaming) with non-default parse
+    /// settings.
+    pub fn open_streaming_with(path: &str, opts: &ParseOptions) -> AlzResult<EntryStream> {
+        let mut reader = MultiVolumeReader::open(path)?;
+        skip_sfx_stub(&mut reader, opts)?;
+        Ok(EntryStream::new(reader, opts))
+    }
+
+    /// Parse and extract an archive from a forward-only stream; see
+    /// [`extract_streaming`](crate::extract::extract_streaming).
+    pub fn extract_streaming<R: Read>(
+        reader: R,
+        sink: &mut dyn ExtractSink,
+        opts: &ExtractOptions,
+    ) -> AlzResult<ExtractSummary> {
+        crate::extract::extract_streaming(reader, sink, opts)
+    }
+
+    /// Parse an archive from an already opened reader, e.g. one built with
+    /// [`MultiVolumeReader::open_with`].
+    pub fn from_reader(reader: MultiVolumeReader<'a>) -> AlzResult<Self> {
+        Self::from_reader_with(reader, &ParseOptions::default())
+    }
+
+    /// [`from_reader`](Self::from_reader) with non-default parse settings.
+    pub fn from_reader_with(
+        mut reader: MultiVolumeReader<'a>,
+        opts: &ParseOptions,
+    ) -> AlzResult<Self> {
+        skip_sfx_stub(&mut reader, opts)?;
+        let mut parser = Parser::new(&reader, opts);
+        let mut entries = Vec::new();
+        while let Some(entry) = parser.next_entry(&mut reader)? {
+            entries.push(entry);
+        }
+        Ok(AlzArchive {
             reader,
-            entries: Vec::new(),
+            entries,
+            is_encrypted: parser.is_encrypted,
+            is_data_descr: parser.is_data_descr,
+            split_markers: parser.split_markers,
+            end_record_seen: parser.end_record_seen,
+            #[cfg(feature = "normalize")]
+            normalization: opts.normalization,
+        })
+    }
+
+    /// An independent copy of the archive with its own read position, e.g.
+    /// for extracting from several threads, without parsing the headers
+    /// again; see [`MultiVolumeReader::try_clone`].
+    ///
+    /// The entry list is copied rather than shared behind an `Arc`, so that
+    /// [`entries`](Self::entries) stays a plain `Vec` callers can index and
+    /// modify. Copying the headers is cheap next to reopening the volumes.
+    pub fn try_clone(&self) -> io::Result<Self> {
+        Ok(AlzArchive {
+            reader: self.reader.try_clone()?,
+            entries: self.entries.clone(),
+            is_encrypted: self.is_encrypted,
+            is_data_descr: self.is_data_descr,
+            split_markers: self.split_markers,
+            end_record_seen: self.end_record_seen,
+            #[cfg(feature = "normalize")]
+            normalization: self.normalization,
+        })
+    }
+
+    /// The file names of all entries, in archive order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().map(|e| e.file_name.as_str())
+    }
+
+    /// Whether the archive has an entry named `name`. `/` and `\` are
+    /// treated alike, so `dir\a.txt` finds `dir/a.txt`.
+    pub fn contains(&self, name: &str) -> bool {
+        let name = self.lookup_name(name);
+        self.entries.iter().any(|e| e.normalized_name() == name)
+    }
+
+    /// [`contains`](Self::contains), ignoring case.
+    pub fn contains_ignore_case(&self, name: &str) -> bool {
+        let name = self.lookup_name(name).to_lowercase();
+        self.entries
+            .iter()
+            .any(|e| e.normalized_name().to_lowercase() == name)
+    }
+
+    /// `name` with `/` separators, in the same normalization form as the
+    /// entry names.
+    fn lookup_name(&self, name: &str) -> String {
+        let name = name.replace('\\', "/");
+        #[cfg(feature = "normalize")]
+        let name = self.normalization.apply(&name);
+        name
+    }
+
+    /// Sum of the uncompressed sizes of all entries, saturating at `u64::MAX`.
+    pub fn total_uncompressed_size(&self) -> u64 {
+        self.entries
+            .iter()
+            .fold(0u64, |acc, e| acc.saturating_add(e.uncompressed_size))
+    }
+
+    /// Bytes [`extract_all_to`](crate::extract::extract_all_to) (with `None`)
+    /// or [`extract_files_to`](crate::extract::extract_files_to) (with the
+    /// same names) would write with `opts`, excluding directories. Follows
+    /// [`ExtractOptions::latest_only`], the size limits and
+    /// [`ExtractOptions::limit`]; files the content filter or `resume` skips
+    /// can only be known while extracting and are still counted. Saturates
+    /// at `u64::MAX`.
+    pub fn extraction_size(&self, files: Option<&[String]>, opts: &ExtractOptions) -> u64 {
+        let selected: Vec<_> = match files {
+            None => {
+                let all: Vec<_> = self.entries.iter().collect();
+                if opts.latest_only {
+                    crate::extract::latest_only(&all)
+                } else {
+                    all
+                }
+            }
+            Some(names) => names
+                .iter()
+                .filter_map(|name| {
+                    crate::extract::find_named(&self.entries, name, opts.latest_only)
+                })
+                .collect(),
+        };
+        selected
+            .into_iter()
+            .filter(|e| !e.is_directory() && opts.size_selected(e.uncompressed_size))
+            .take(opts.limit.unwrap_or(usize::MAX))
+            .fold(0u64, |acc, e| acc.saturating_add(e.uncompressed_size))
+    }
+
+    /// Whether extracting all entries (`None`) or the named ones would need
+    /// a password. Unlike [`is_encrypted`](Self::is_encrypted), this is
+    /// `false` when only entries outside the selection are encrypted.
+    pub fn needs_password(&self, files: Option<&[String]>) -> bool {
+        match files {
+            None => self.entries.iter().any(|e| e.is_encrypted()),
+            Some(names) => names.iter().any(|name| {
+                self.entries
+                    .iter()
+                    .any(|e| e.file_name == *name && e.is_encrypted())
+            }),
+        }
+    }
+
+    /// Sum of the compressed sizes of all entries, saturating at `u64::MAX`.
+    pub fn total_compressed_size(&self) -> u64 {
+        self.entries
+            .iter()
+            .fold(0u64, |acc, e| acc.saturating_add(e.compressed_size))
+    }
+
+    /// Number of entries that are not directories.
+    pub fn file_count(&self) -> usize {
+        self.entries.iter().filter(|e| !e.is_directory()).count()
+    }
+
+    /// Read `len` raw bytes at virtual `offset`, across volume boundaries,
+    /// e.g. to inspect a header or padding. The range must lie within
+    /// [`MultiVolumeReader::total_size`].
+    pub fn read_at(&mut self, offset: u64, len: usize) -> AlzResult<Vec<u8>> {
+        let total = self.reader.total_size();
+        if offset.checked_add(len as u64).is_none_or(|end| end > total) {
+            return Err(AlzError::Io(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "{len} bytes at offset {offset} run past the end of the archive ({total} bytes)"
+                ),
+            )));
+        }
+        let mut buf = vec![0; len];
+        self.reader.seek(SeekFrom::Start(offset))?;
+        self.reader.read_exact(&mut buf)?;
+        Ok(buf)
+    }
+
+    /// Indices of the volumes holding `entry`'s data, in order.
+    ///
+    /// Empty for entries without data.
+    pub fn entry_volumes(&self, entry: &AlzFileEntry) -> Vec<usize> {
+        self.reader
+            .locate(entry.data_pos, entry.compressed_size)
+            .into_iter()
+            .map(|(i, _, _)| i)
+            .collect()
+    }
+
+    /// Where `entry`'s stored data lies on disk, as `(volume path, offset,
+    /// length)` for each volume holding part of it, in order.
+    ///
+    /// The bytes are the data as stored: for an unencrypted Store entry
+    /// they are the file contents, which can then be read or memory-mapped
+    /// straight from the volume files. Empty for entries without data and
+    /// for archives not opened from files.
+    pub fn physical_location(&self, entry: &AlzFileEntry) -> Vec<(PathBuf, u64, u64)> {
+        self.reader
+            .locate(entry.data_pos, entry.compressed_size)
+            .into_iter()
+            .filter_map(|(i, offset, len)| {
+                let path = self.reader.volume_path(i)?;
+                Some((path.to_path_buf(), offset, len))
+            })
+            .collect()
+    }
+
+    /// The parsed file tail; the raw bytes are [`MultiVolumeReader::tail`].
+    pub fn tail_info(&self) -> TailInfo {
+        TailInfo::parse(self.reader.tail())
+    }
+
+    /// Number of volume files the archive was opened from.
+    pub fn volume_count(&self) -> usize {
+        self.reader.volume_count()
+    }
+
+    /// Whether the archive was reassembled from more than one volume file.
+    pub fn is_split(&self) -> bool {
+        self.volume_count() > 1
+    }
+
+    /// Iterate over the entries.
+    pub fn iter(&self) -> std::slice::Iter<'_, AlzFileEntry> {
+        self.entries.iter()
+    }
+
+    /// Borrow the reader mutably alongside the entry list.
+    ///
+    /// Lets callers extract while iterating entries by reference, without
+    /// cloning the list to satisfy the borrow checker.
+    pub fn reader_and_entries(&mut self) -> (&mut MultiVolumeReader<'a>, &[AlzFileEntry]) {
+        (&mut self.reader, &self.entries)
+    }
+
+    /// Check the parsed layout for signs of truncation or tampering without
+    /// reading any entry data.
+    ///
+    /// Every record signature has already been parsed by the time an
+    /// archive is open; this checks what parsing tolerates: entry data
+    /// running past the end of the available volumes, Store entries whose
+    /// two sizes disagree, data behind a sizeless header and a missing end
+    /// record. The central directory carries no per-entry data in this
+    /// format, so there is nothing to compare against the local headers.
+    pub fn validate_structure(&self) -> Vec<StructuralIssue> {
+        let available = self.reader.total_size();
+        let mut issues = Vec::new();
+        for (index, entry) in self.entries.iter().enumerate() {
+            let offset = entry.data_pos;
+            let end = offset.saturating_add(entry.compressed_size);
+            if end > available {
+                issues.push(StructuralIssue::DataOutOfBounds {
+                    index,
+                    offset,
+                    end,
+                    available,
+                });
+            }
+            if entry.size_unknown {
+                issues.push(StructuralIssue::SizeUnknown { index, offset });
+            } else if entry.compression_method == CompressionMethod::Store
+                && !entry.is_directory()
+                && entry.compressed_size != entry.uncompressed_size
+            {
+                issues.push(StructuralIssue::StoreSizeMismatch {
+                    index,
+                    offset,
+                    compressed: entry.compressed_size,
+                    uncompressed: entry.uncompressed_size,
+                });
+            }
+        }
+        if !self.end_record_seen {
+            issues.push(StructuralIssue::MissingEndRecord { offset: available });
+        }
+        issues
+    }
+
+    /// List entries that use features this crate cannot extract, so callers
+    /// can fail before extracting anything.
+    pub fn unsupported_features(&self) -> Vec<UnsupportedFeature> {
+        let mut found = Vec::new();
+        for entry in &self.entries {
+            if entry.size_unknown {
+                found.push(UnsupportedFeature::SizeUnknown {
+                    file_name: entry.file_name.clone(),
+                });
+            }
+            if let CompressionMethod::Unknown(method) = entry.compression_method {
+                found.push(UnsupportedFeature::CompressionMethod {
+                    file_name: entry.file_name.clone(),
+                    method,
+                });
+            }
+            let flags = entry.file_descriptor & !DESC_KNOWN;
+            if flags != 0 {
+                found.push(UnsupportedFeature::DescriptorFlags {
+                    file_name: entry.file_name.clone(),
+                    flags,
+                });
+            }
+        }
+        found
+    }
+}
+
+impl<'a> IntoIterator for &'a AlzArchive<'_> {
+    type Item = &'a AlzFileEntry;
+    type IntoIter = std::slice::Iter<'a, AlzFileEntry>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.iter()
+    }
+}
+
+/// A feature used by an archive entry that extraction does not support.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UnsupportedFeature {
+    /// Compression method byte other than Store, Bzip2 or Deflate.
+    CompressionMethod { file_name: String, method: u8 },
+    /// File descriptor bits with no known meaning (possibly another
+    /// encryption scheme); the entry may not extract correctly.
+    DescriptorFlags { file_name: String, flags: u8 },
+    /// Data follows a header without size fields; see
+    /// [`AlzFileEntry::size_unknown`].
+    SizeUnknown { file_name: String },
+}
+
+impl std::fmt::Display for UnsupportedFeature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::CompressionMethod { file_name, method } => {
+                write!(f, "{file_name}: unknown compression method {method}")
+            }
+            Self::DescriptorFlags { file_name, flags } => {
+                write!(f, "{file_name}: unknown descriptor flags 0x{flags:02x}")
+            }
+            Self::SizeUnknown { file_name } => {
+                write!(f, "{file_name}: data present but header has no sizes")
+            }
+        }
+    }
+}
+
+/// An anomaly found by [`AlzArchive::validate_structure`]. `index` is the
+/// entry's position in [`AlzArchive::entries`] and `offset` the virtual
+/// offset of its data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StructuralIssue {
+    /// The entry's data ends past the last available byte, e.g. because
+    /// the archive is truncated or a volume is missing.
+    DataOutOfBounds {
+        index: usize,
+        offset: u64,
+        end: u64,
+        available: u64,
+    },
+    /// A Store entry whose compressed and uncompressed sizes differ.
+    StoreSizeMismatch {
+        index: usize,
+        offset: u64,
+        compressed: u64,
+        uncompressed: u64,
+    },
+    /// Data follows a header without size fields; see
+    /// [`AlzFileEntry::size_unknown`].
+    SizeUnknown { index: usize, offset: u64 },
+    /// Input ended before the end-of-central-directory record.
+    MissingEndRecord { offset: u64 },
+}
+
+impl std::fmt::Display for StructuralIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::DataOutOfBounds {
+                index,
+                offset,
+                end,
+                available,
+            } => write!(
+                f,
+                "entry {index} at {offset}: data ends at {end}, past the archive end at {available}"
+            ),
+            Self::StoreSizeMismatch {
+                index,
+                offset,
+                compressed,
+                uncompressed,
+            } => write!(
+                f,
+                "entry {index} at {offset}: stored entry has compressed size {compressed} but uncompressed size {uncompressed}"
+            ),
+            Self::SizeUnknown { index, offset } => {
+                write!(
+                    f,
+                    "entry {index} at {offset}: data present but header has no sizes"
+                )
+            }
+            Self::MissingEndRecord { offset } => {
+                write!(f, "no end-of-central-directory record before {offset}")
+            }
+        }
+    }
+}
+
+/// Iterator over the entries of an archive, parsing headers on demand.
+///
+/// Returned by [`AlzArchive::open_streaming`]. Iteration stops after the
+/// end-of-central-directory record or the first error.
+pub struct EntryStream {
+    reader: MultiVolumeReader<'static>,
+    parser: Parser,
+}
+
+impl EntryStream {
+    fn new(reader: MultiVolumeReader<'static>, opts: &ParseOptions) -> Self {
+        let parser = Parser::new(&reader, opts);
+        EntryStream { reader, parser }
+    }
+
+    /// Whether any entry seen so far is encrypted.
+    pub fn is_encrypted(&self) -> bool {
+        self.parser.is_encrypted
+    }
+
+    /// Number of volume files the archive was opened from.
+    pub fn volume_count(&self) -> usize {
+        self.reader.volume_count()
+    }
+}
+
+impl Iterator for EntryStream {
+    type Item = AlzResult<AlzFileEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.parser.next_entry(&mut self.reader) {
+            Ok(entry) => entry.map(Ok),
+            Err(e) => {
+                self.parser.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// The 16-byte file tail (`endInfos`) at the end of the first volume; see
+/// section 9 of `docs/specification.md`.
+///
+/// Only the comment section size is understood. The other three words are
+/// kept as stored so they can be compared across archives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TailInfo {
+    /// `endInfos[0]`, offset 0; meaning unknown.
+    pub end_info0: u32,
+    /// `endInfos[1]`, offset 4: total size of the comment section,
+    /// including its `ELZ\x01` signature. 4 or less means no comment data.
+    pub comment_section_size: u32,
+    /// `endInfos[2]`, offset 8; meaning unknown.
+    pub end_info2: u32,
+    /// `endInfos[3]`, offset 12; meaning unknown.
+    pub end_info3: u32,
+}
+
+impl TailInfo {
+    /// Split the raw tail into its four little-endian words.
+    pub fn parse(tail: &[u8; 16]) -> Self {
+        let word = |i: usize| u32::from_le_bytes([tail[i], tail[i + 1], tail[i + 2], tail[i + 3]]);
+        TailInfo {
+            end_info0: word(0),
+            comment_section_size: word(4),
+            end_info2: word(8),
+            end_info3: word(12),
+        }
+    }
+
+    /// Whether the archive has comment data to skip.
+    pub fn has_comments(&self) -> bool {
+        self.comment_section_size > 4
+    }
+}
+
+/// Sequential record parser state.
+pub(crate) struct Parser {
+    seen_alz_header: bool,
+    /// Comment section size from the file tail; `None` when reading a
+    /// forward-only stream, where the tail is not available.
+    comment_section_size: Option<u64>,
+    /// Signature already read while scanning a comment section.
+    pending_sig: Option<u32>,
+    done: bool,
+    pub(crate) is_encrypted: bool,
+    is_data_descr: bool,
+    split_markers: u32,
+    end_record_seen: bool,
+    require_end_record: bool,
+    name_decoding: NameDecoding,
+    #[cfg(feature = "normalize")]
+    normalization: NameNormalization,
+}
+
+impl Parser {
+    fn new(reader: &MultiVolumeReader, opts: &ParseOptions) -> Self {
+        let tail = TailInfo::parse(reader.tail());
+        Parser {
+            comment_section_size: Some(tail.comment_section_size as u64),
+            name_decoding: opts.name_decoding,
+            require_end_record: opts.require_end_record,
+            #[cfg(feature = "normalize")]
+            normalization: opts.normalization,
+            ..Self::streaming()
+        }
+    }
+
+    /// Parser for a forward-only stream, without a file tail.
+    pub(crate) fn streaming() -> Self {
+        Parser {
+            seen_alz_header: false,
+            comment_section_size: None,
+            pending_sig: None,
+            done: false,
             is_encrypted: false,
             is_data_descr: false,
-        };
-        archive.parse()?;
-        Ok(archive)
+            split_markers: 0,
+            end_record_seen: false,
+            require_end_record: false,
+            name_decoding: NameDecoding::default(),
+            #[cfg(feature = "normalize")]
+            normalization: NameNormalization::default(),
+        }
     }
 
-    fn parse(&mut self) -> AlzResult<()> {
-        let mut seen_alz_header = false;
+    /// Read records until the next local file header and skip its data.
+    /// Returns `None` once the end of the archive is reached.
+    fn next_entry(&mut self, reader: &mut MultiVolumeReader) -> AlzResult<Option<AlzFileEntry>> {
+        let Some(mut entry) = self.next_header(reader)? else {
+            return Ok(None);
+        };
+        if entry.size_unknown {
+            // The data was scanned over already, up to the next signature.
+            entry.data_pos = reader
+                .stream_position()?
+                .checked_sub(self.pending_len() + entry.compressed_size)
+                .ok_or(AlzError::CorruptedFile)?;
+            return Ok(Some(entry));
+        }
+        // Record data position and skip file data. A sizeless empty entry
+        // has had the next signature read already.
+        entry.data_pos = reader
+            .stream_position()?
+            .checked_sub(self.pending_len())
+            .ok_or(AlzError::CorruptedFile)?;
+        let skip: i64 = entry
+            .compressed_size
+            .try_into()
+            .map_err(|_| AlzError::CorruptedFile)?;
+        reader.seek(SeekFrom::Current(skip))?;
+        Ok(Some(entry))
+    }
 
-        // Parse endInfos from the 16-byte file tail.
-        let tail = *self.reader.tail();
-        let comment_section_size = u32::from_le_bytes([tail[4], tail[5], tail[6], tail[7]]) as u64;
+    /// Bytes read ahead of the reader position (a record signature).
+    pub(crate) fn pending_len(&self) -> u64 {
+        if self.pending_sig.is_some() { 4 } else { 0 }
+    }
 
-        while let Ok(sig) = self.read_u32_le() {
+    /// Read records until the next local file header, leaving `reader` at
+    /// the start of its data. Reads forward only.
+    pub(crate) fn next_header<R: Read>(
+        &mut self,
+        reader: &mut R,
+    ) -> AlzResult<Option<AlzFileEntry>> {
+        while !self.done {
+            let sig = match self.pending_sig.take() {
+                Some(sig) => sig,
+                None => {
+                    let Ok(sig) = read_u32_le(reader) else {
+                        self.done = true;
+                        if self.require_end_record {
+                            return Err(AlzError::MissingEndRecord);
+                        }
+                        break;
+                    };
+                    sig
+                }
+            };
             match sig {
                 SIG_ALZ_FILE_HEADER => {
-                    self.read_alz_header()?;
-                    seen_alz_header = true;
+                    read_alz_header(reader)?;
+                    self.seen_alz_header = true;
                 }
                 SIG_LOCAL_FILE_HEADER => {
-                    self.read_local_file_header()?;
+                    let mut entry = read_local_file_header(reader, self.name_decoding)?;
+                    #[cfg(feature = "normalize")]
+                    if self.normalization != NameNormalization::None {
+                        entry.file_name = self.normalization.apply(&entry.file_name);
+                    }
+                    if entry.uncompressed_size == 0
+                        && entry.file_descriptor & DESC_SIZE_MASK == 0
+                        && !entry.is_directory()
+                    {
+                        // No size fields: normally an empty file. Look for
+                        // the next record to tell whether data follows.
+                        match read_u32_le(reader) {
+                            Ok(sig) if is_signature(sig) => self.pending_sig = Some(sig),
+                            Ok(first) => {
+                                let mut chained =
+                                    io::Cursor::new(first.to_le_bytes()).chain(&mut *reader);
+                                let (len, sig) = scan_to_signature(&mut chained)?;
+                                entry.size_unknown = true;
+                                entry.compressed_size = len;
+                                self.pending_sig = sig;
+                            }
+                            Err(_) => {}
+                        }
+                    }
+                    if entry.is_encrypted() {
+                        self.is_encrypted = true;
+                    }
+                    if entry.has_data_descriptor() {
+                        self.is_data_descr = true;
+                    }
+                    return Ok(Some(entry));
                 }
                 SIG_CENTRAL_DIRECTORY => {
-                    self.read_central_directory()?;
+                    read_central_directory(reader)?;
                 }
                 SIG_END_OF_CENTRAL_DIR => {
-                    break;
+                    self.end_record_seen = true;
+                    self.done = true;
                 }
-                SIG_COMMENT => {
-                    self.skip_comment_section(comment_section_size)?;
+                SIG_COMMENT => match self.comment_section_size {
+                    Some(size) => skip_comment_section(reader, size)?,
+                    None => self.pending_sig = skip_comment_entries(reader)?,
+                },
+                // A bare signature with no payload, as in the reference
+                // implementation. If it did carry data, the next signature
+                // read would fail and report CorruptedFile.
+                SIG_SPLIT_MARKER => {
+                    self.split_markers += 1;
                 }
-                SIG_SPLIT_MARKER => {}
                 _ => {
-                    if seen_alz_header {
+                    self.done = true;
+                    if self.seen_alz_header {
                         return Err(AlzError::CorruptedFile);
-                    } else {
-                        return Err(AlzError::NotAlzFile);
                     }
+                    return Err(match format::detect(&sig.to_le_bytes()) {
+                        ArchiveFormat::Zip => AlzError::IsZipNotAlz,
+                        ArchiveFormat::Alz | ArchiveFormat::Unknown => AlzError::NotAlzFile,
+                        other => AlzError::UnsupportedFormat(other),
+                    });
                 }
             }
         }
 
-        Ok(())
+        Ok(None)
     }
+}
 
-    fn read_alz_header(&mut self) -> AlzResult<()> {
-        // 2 bytes version + 2 bytes ID
-        let mut buf = [0u8; 4];
-        self.reader.read_exact(&mut buf)?;
-        Ok(())
+/// With [`ParseOptions::sfx_scan_limit`], find the archive signature in
+/// the leading bytes and make it the reader's offset 0. The 8-byte archive
+/// header has to be followed by another record, which rules out stray
+/// copies of the signature inside a stub.
+fn skip_sfx_stub(reader: &mut MultiVolumeReader, opts: &ParseOptions) -> AlzResult<()> {
+    let Some(limit) = opts.sfx_scan_limit else {
+        return Ok(());
+    };
+    let mut sig = [0u8; 4];
+    let at_start = reader.read_exact(&mut sig).is_ok() && sig == *b"ALZ\x01";
+    reader.seek(SeekFrom::Start(0))?;
+    if at_start {
+        return Ok(());
     }
+    let mut head = Vec::new();
+    (&mut *reader)
+        .take(limit.saturating_add(12))
+        .read_to_end(&mut head)?;
+    reader.seek(SeekFrom::Start(0))?;
+    let start = head.windows(12).position(|w| {
+        w.starts_with(b"ALZ\x01") && is_signature(u32::from_le_bytes([w[8], w[9], w[10], w[11]]))
+    });
+    if let Some(start) = start {
+        reader.skip_prefix(start as u64)?;
+    }
+    Ok(())
+}
+
+fn read_alz_header<R: Read>(reader: &mut R) -> AlzResult<()> {
+    // 2 bytes version + 2 bytes ID
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(())
+}
 
-    fn read_local_file_header(&mut self) -> AlzResult<()> {
-        // Fixed header: 9 bytes
-        let mut head = [0u8; 9];
-        self.reader.read_exact(&mut head)?;
+/// Read a local file header (after its signature), up to the start of the
+/// file data. The returned entry's `data_pos` is left for the caller to set.
+fn read_local_file_header<R: Read>(
+    reader: &mut R,
+    name_decoding: NameDecoding,
+) -> AlzResult<AlzFileEntry> {
+    // Fixed header: 9 bytes
+    let mut head = [0u8; 9];
+    reader.read_exact(&mut head)?;
 
-        let file_name_length = u16::from_le_bytes([head[0], head[1]]) as usize;
-        let file_attribute = head[2];
-        let file_time_date = u32::from_le_bytes([head[3], head[4], head[5], head[6]]);
-        let file_descriptor = head[7];
-        let _unknown2 = head[8];
+    let file_name_length = u16::from_le_bytes([head[0], head[1]]) as usize;
+    let file_attribute = head[2];
+    let file_time_date = u32::from_le_bytes([head[3], head[4], head[5], head[6]]);
+    let file_descriptor = head[7];
+    let mut raw_reserved = [head[8], 0];
 
-        // Check encryption
-        if file_descriptor & DESC_ENCRYPTED != 0 {
-            self.is_encrypted = true;
-        }
-        if file_descriptor & DESC_DATA_DESCR != 0 {
-            self.is_data_descr = true;
+    // Size field width from descriptor bits 4-7. The format only defines
+    // these five widths (specification 4.3); 3-byte (0x30) and other
+    // nibbles are errors rather than guesses.
+    let byte_len = match file_descriptor & DESC_SIZE_MASK {
+        0x00 => 0,
+        0x10 => 1,
+        0x20 => 2,
+        0x40 => 4,
+        0x80 => 8,
+        _ => {
+            return Err(AlzError::InvalidSizeFieldWidth(
+                file_descriptor & DESC_SIZE_MASK,
+            ));
         }
+    };
 
-        // Size field width from descriptor bits 4-7
-        let byte_len = match file_descriptor & 0xF0 {
-            0x00 => 0,
-            0x10 => 1,
-            0x20 => 2,
-            0x40 => 4,
-            0x80 => 8,
-            _ => return Err(AlzError::InvalidSizeFieldWidth(file_descriptor & 0xF0)),
-        };
+    let mut compression_method = CompressionMethod::Store;
+    let mut file_crc: u32 = 0;
+    let mut compressed_size: u64 = 0;
+    let mut uncompressed_size: u64 = 0;
 
-        let mut compression_method = CompressionMethod::Store;
-        let mut file_crc: u32 = 0;
-        let mut compressed_size: u64 = 0;
-        let mut uncompressed_size: u64 = 0;
+    if byte_len > 0 {
+        // compression method (1 byte)
+        let mut cm = [0u8; 1];
+        reader.read_exact(&mut cm)?;
+        compression_method = CompressionMethod::from_byte(cm[0]);
 
-        if byte_len > 0 {
-            // compression method (1 byte)
-            let mut cm = [0u8; 1];
-            self.reader.read_exact(&mut cm)?;
-            compression_method = CompressionMethod::from_byte(cm[0]);
+        // unknown (1 byte)
+        let mut unk = [0u8; 1];
+        reader.read_exact(&mut unk)?;
+        raw_reserved[1] = unk[0];
 
-            // unknown (1 byte)
-            let mut unk = [0u8; 1];
-            self.reader.read_exact(&mut unk)?;
+        // file CRC (4 bytes)
+        let mut crc_buf = [0u8; 4];
+        reader.read_exact(&mut crc_buf)?;
+        file_crc = u32::from_le_bytes(crc_buf);
 
-            // file CRC (4 bytes)
-            let mut crc_buf = [0u8; 4];
-            self.reader.read_exact(&mut crc_buf)?;
-            file_crc = u32::from_le_bytes(crc_buf);
+        // compressed size (byte_len bytes)
+        compressed_size = read_var_int(reader, byte_len)?;
 
-            // compressed size (byte_len bytes)
-            compressed_size = self.read_var_int(byte_len)?;
+        // uncompressed size (byte_len bytes)
+        uncompressed_size = read_var_int(reader, byte_len)?;
 
-            // uncompressed size (byte_len bytes)
-            uncompressed_size = self.read_var_int(byte_len)?;
+        // Directories carry no data, but some archivers write a bogus size
+        // for them; skipping it would overrun the next record.
+        if file_attribute & ATTR_DIRECTORY != 0 {
+            compressed_size = 0;
+            uncompressed_size = 0;
         }
+    }
 
-        // File name
-        if file_name_length == 0 || file_name_length > 4096 {
-            return Err(AlzError::InvalidFilenameLength);
-        }
-        let mut name_buf = vec![0u8; file_name_length];
-        self.reader.read_exact(&mut name_buf)?;
-        let file_name = cp949_to_utf8(&name_buf);
-
-        // Encryption header
-        let enc_check = if file_descriptor & DESC_ENCRYPTED != 0 {
-            let mut buf = [0u8; ENCR_HEADER_LEN];
-            self.reader.read_exact(&mut buf)?;
-            Some(buf)
-        } else {
-            None
-        };
+    // File name
+    if file_name_length == 0 || file_name_length > 4096 {
+        return Err(AlzError::InvalidFilenameLength);
+    }
+    let mut name_buf = vec![0u8; file_name_length];
+    reader.read_exact(&mut name_buf)?;
+    let file_name = name_decoding.decode(&name_buf);
 
-        // Record data position and skip file data
-        let data_pos = self.reader.stream_position()?;
-        let skip: i64 = compressed_size
-            .try_into()
-            .map_err(|_| AlzError::CorruptedFile)?;
-        self.reader.seek(SeekFrom::Current(skip))?;
-
-        self.entries.push(AlzFileEntry {
-            file_name,
-            file_attribute,
-            file_time_date,
-            file_descriptor,
-            compression_method,
-            file_crc,
-            compressed_size,
-            uncompressed_size,
-            data_pos,
-            enc_check,
-        });
-
-        Ok(())
-    }
-
-    fn read_central_directory(&mut self) -> AlzResult<()> {
-        // Central directory structure head: 12 bytes (3 x u32)
-        let mut buf = [0u8; 12];
-        self.reader.read_exact(&mut buf)?;
-        Ok(())
+    // Encryption header
+    let enc_check = if file_descriptor & DESC_ENCRYPTED != 0 {
+        let mut buf = [0u8; ENCR_HEADER_LEN];
+        reader.read_exact(&mut buf)?;
+        Some(buf)
+    } else {
+        None
+    };
+
+    Ok(AlzFileEntry {
+        file_name,
+        file_attribute,
+        file_time_date,
+        file_descriptor,
+        compression_method,
+        file_crc,
+        compressed_size,
+        uncompressed_size,
+        data_pos: 0,
+        enc_check,
+        raw_reserved,
+        size_unknown: false,
+    })
+}
+
+fn read_central_directory<R: Read>(reader: &mut R) -> AlzResult<()> {
+    // Central directory structure head: 12 bytes (3 x u32)
+    let mut buf = [0u8; 12];
+    reader.read_exact(&mut buf)?;
+    Ok(())
+}
+
+fn skip_comment_section<R: Read>(reader: &mut R, total_size: u64) -> AlzResult<()> {
+    // total_size includes the 4-byte signature we already read.
+    if total_size > 4 {
+        io::copy(&mut reader.take(total_size - 4), &mut io::sink())?;
+    }
+    Ok(())
+}
+
+fn is_signature(word: u32) -> bool {
+    matches!(
+        word,
+        SIG_ALZ_FILE_HEADER
+            | SIG_LOCAL_FILE_HEADER
+            | SIG_CENTRAL_DIRECTORY
+            | SIG_END_OF_CENTRAL_DIR
+            | SIG_COMMENT
+            | SIG_SPLIT_MARKER
+    )
+}
+
+/// Consume bytes up to and including the next record signature. Returns
+/// the number of bytes before it and the signature, or `None` for the
+/// signature at end of input.
+fn scan_to_signature<R: Read>(reader: &mut R) -> AlzResult<(u64, Option<u32>)> {
+    let mut window = read_u32_le(reader)?;
+    let mut skipped = 0u64;
+    loop {
+        if is_signature(window) {
+            return Ok((skipped, Some(window)));
+        }
+        let mut b = [0u8; 1];
+        if reader.read(&mut b)? == 0 {
+            return Ok((skipped + 4, None));
+        }
+        window = (window >> 8) | (u32::from(b[0]) << 24);
+        skipped += 1;
     }
+}
 
-    fn skip_comment_section(&mut self, total_size: u64) -> AlzResult<()> {
-        // total_size includes the 4-byte signature we already read.
-        if total_size > 4 {
-            let skip: i64 = (total_size - 4)
-                .try_into()
-                .map_err(|_| AlzError::CorruptedFile)?;
-            self.reader.seek(SeekFrom::Current(skip))?;
+/// Skip comment entries without knowing the section size, stopping at the
+/// first record signature. Returns that signature, or `None` at end of input.
+fn skip_comment_entries<R: Read>(reader: &mut R) -> AlzResult<Option<u32>> {
+    loop {
+        let Ok(word) = read_u32_le(reader) else {
+            return Ok(None);
+        };
+        if is_signature(word) {
+            return Ok(Some(word));
         }
-        Ok(())
+        // `word` was a file index; skip the comment text.
+        let mut len = [0u8; 2];
+        reader.read_exact(&mut len)?;
+        let len = u16::from_le_bytes(len) as u64;
+        io::copy(&mut reader.take(len), &mut io::sink())?;
     }
+}
 
-    fn read_u32_le(&mut self) -> AlzResult<u32> {
-        let mut buf = [0u8; 4];
-        self.reader.read_exact(&mut buf)?;
-        Ok(u32::from_le_bytes(buf))
+fn read_u32_le<R: Read>(reader: &mut R) -> AlzResult<u32> {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+/// Read a variable-width little-endian integer (1, 2, 4, or 8 bytes).
+/// Widths over 8 fail with [`AlzError::CorruptedFile`] rather than panic.
+pub(crate) fn read_var_int<R: Read>(reader: &mut R, byte_len: usize) -> AlzResult<u64> {
+    if byte_len > 8 {
+        return Err(AlzError::CorruptedFile);
     }
+    let mut buf = [0u8; 8];
+    reader.read_exact(&mut buf[..byte_len])?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    /// Read a variable-width little-endian integer (1, 2, 4, or 8 bytes).
-    fn read_var_int(&mut self, byte_len: usize) -> AlzResult<u64> {
-        let mut buf = [0u8; 8];
-        self.reader.read_exact(&mut buf[..byte_len])?;
-        Ok(u64::from_le_bytes(buf))
+    #[test]
+    fn test_read_var_int_widths() {
+        let bytes = [0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09];
+        assert_eq!(read_var_int(&mut &bytes[..], 0).unwrap(), 0);
+        assert_eq!(read_var_int(&mut &bytes[..], 1).unwrap(), 0x01);
+        assert_eq!(read_var_int(&mut &bytes[..], 2).unwrap(), 0x0201);
+        assert_eq!(read_var_int(&mut &bytes[..], 4).unwrap(), 0x04030201);
+        assert_eq!(
+            read_var_int(&mut &bytes[..], 8).unwrap(),
+            0x0807060504030201
+        );
+        assert!(matches!(
+            read_var_int(&mut &bytes[..], 9),
+            Err(AlzError::CorruptedFile)
+        ));
+        assert!(read_var_int(&mut &bytes[..2], 4).is_err(), "short input");
     }
 }
@@ -2,6 +2,8 @@
 //! Keys initialized to [305419896, 591751049, 878082192].
 //! Uses CRC32 table for key updates.
 
+use std::io::{self, Read};
+
 pub const ENCR_HEADER_LEN: usize = 12;
 
 /// Standard CRC32 lookup table (polynomial 0xEDB88320).
@@ -25,6 +27,19 @@ const CRC32_TABLE: [u32; 256] = {
     table
 };
 
+/// Outcome of [`ZipCrypto::check_header_detailed`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeaderCheck {
+    /// The check byte matched.
+    Ok,
+    /// The check byte did not match the top byte of the file CRC (entry
+    /// without a data descriptor).
+    CrcByteMismatch,
+    /// The check byte did not match bits 8-15 of the DOS time (entry with a
+    /// data descriptor).
+    TimeByteMismatch,
+}
+
 pub struct ZipCrypto {
     key: [u32; 3],
 }
@@ -56,6 +71,27 @@ impl ZipCrypto {
         ((temp.wrapping_mul(temp ^ 1)) >> 8) as u8
     }
 
+    /// Build an encrypted 12-byte encryption header from `padding` followed
+    /// by `check`, and advance the keys past it so the entry data can be
+    /// encrypted next.
+    ///
+    /// `check` is the byte [`check_header`](Self::check_header) verifies:
+    /// the top byte of the file CRC, or bits 8-15 of the DOS time when the
+    /// entry has a data descriptor. `padding` must be fresh bytes from a
+    /// cryptographically secure source (e.g. the `getrandom` crate) for
+    /// every entry; reused or predictable padding weakens the cipher.
+    pub fn make_header(
+        &mut self,
+        check: u8,
+        padding: [u8; ENCR_HEADER_LEN - 1],
+    ) -> [u8; ENCR_HEADER_LEN] {
+        let mut header = [0u8; ENCR_HEADER_LEN];
+        header[..ENCR_HEADER_LEN - 1].copy_from_slice(&padding);
+        header[ENCR_HEADER_LEN - 1] = check;
+        self.encrypt(&mut header);
+        header
+    }
+
     /// Validate the 12-byte encryption header.
     /// Returns true if password is correct.
     pub fn check_header(
@@ -65,6 +101,20 @@ impl ZipCrypto {
         file_time_date: u32,
         is_data_descr: bool,
     ) -> bool {
+        self.check_header_detailed(enc_header, file_crc, file_time_date, is_data_descr)
+            == HeaderCheck::Ok
+    }
+
+    /// Like [`check_header`](Self::check_header), but on failure says which
+    /// check byte was compared. A wrong password fails either way; a
+    /// mismatch on the unexpected kind hints at a wrong data descriptor flag.
+    pub fn check_header_detailed(
+        &mut self,
+        enc_header: &[u8; ENCR_HEADER_LEN],
+        file_crc: u32,
+        file_time_date: u32,
+        is_data_descr: bool,
+    ) -> HeaderCheck {
         let mut last_byte = 0u8;
         for &b in enc_header.iter() {
             let c = b ^ self.decrypt_byte();
@@ -73,13 +123,22 @@ impl ZipCrypto {
         }
 
         if is_data_descr {
-            (file_time_date >> 8) as u8 == last_byte
+            if (file_time_date >> 8) as u8 == last_byte {
+                HeaderCheck::Ok
+            } else {
+                HeaderCheck::TimeByteMismatch
+            }
+        } else if (file_crc >> 24) as u8 == last_byte {
+            HeaderCheck::Ok
         } else {
-            (file_crc >> 24) as u8 == last_byte
+            HeaderCheck::CrcByteMismatch
         }
     }
 
     /// Decrypt data in place.
+    ///
+    /// The keys are updated with each byte *after* it is decrypted, i.e.
+    /// always with the plaintext; see [`encrypt`](Self::encrypt).
     pub fn decrypt(&mut self, data: &mut [u8]) {
         for b in data.iter_mut() {
             let temp = *b ^ self.decrypt_byte();
@@ -87,11 +146,79 @@ impl ZipCrypto {
             *b = temp;
         }
     }
+
+    /// Encrypt data in place; the inverse of [`decrypt`](Self::decrypt).
+    ///
+    /// Each byte is XORed with the keystream first and the keys are then
+    /// updated with the *plaintext* byte, not the ciphertext just produced.
+    /// Updating with the output (as one would when mirroring `decrypt`
+    /// line by line) gives data nothing can decrypt.
+    pub fn encrypt(&mut self, data: &mut [u8]) {
+        for b in data.iter_mut() {
+            let plain = *b;
+            *b = plain ^ self.decrypt_byte();
+            self.update_keys(plain);
+        }
+    }
+}
+
+/// Reader adapter that decrypts everything read through it.
+/// Passes data through unchanged when `crypto` is `None`.
+pub struct DecryptReader<R> {
+    inner: R,
+    crypto: Option<ZipCrypto>,
+}
+
+impl<R: Read> DecryptReader<R> {
+    pub fn new(inner: R, crypto: Option<ZipCrypto>) -> Self {
+        DecryptReader { inner, crypto }
+    }
+}
+
+impl<R: Read> Read for DecryptReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        if let Some(ref mut c) = self.crypto {
+            c.decrypt(&mut buf[..n]);
+        }
+        Ok(n)
+    }
+}
+
+/// Known-answer checks for the CRC table and cipher, for runtime self-tests.
+pub(crate) fn self_test() -> Result<(), String> {
+    for (i, want) in [(0usize, 0x00000000u32), (1, 0x77073096), (255, 0x2D02EF8D)] {
+        if CRC32_TABLE[i] != want {
+            return Err(format!(
+                "CRC32 table entry {i}: expected {want:08x}, got {:08x}",
+                CRC32_TABLE[i]
+            ));
+        }
+    }
+
+    let c = ZipCrypto::new(b"");
+    if c.key != [305419896, 591751049, 878082192] {
+        return Err(format!("ZipCrypto initial keys: got {:?}", c.key));
+    }
+
+    let data = *b"hello world";
+    let mut buf = data;
+    ZipCrypto::new(b"secret").encrypt(&mut buf);
+    if buf == data {
+        return Err("ZipCrypto encryption left data unchanged".into());
+    }
+    let mut c = ZipCrypto::new(b"secret");
+    c.decrypt(&mut buf);
+    if buf != data {
+        return Err("ZipCrypto roundtrip mismatch".into());
+    }
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use proptest::prelude::*;
 
     #[test]
     fn test_initial_keys() {
@@ -117,19 +244,79 @@ mod tests {
     fn test_encrypt_decrypt_roundtrip() {
         let data = b"hello world";
         let mut encrypted = *data;
+        ZipCrypto::new(b"secret").encrypt(&mut encrypted);
+        assert_ne!(&encrypted, data);
 
-        // Encrypt
+        ZipCrypto::new(b"secret").decrypt(&mut encrypted);
+        assert_eq!(&encrypted, data);
+    }
+
+    #[test]
+    fn test_make_header_checks() {
         let mut c = ZipCrypto::new(b"secret");
-        for b in encrypted.iter_mut() {
-            let plain = *b;
-            *b = plain ^ c.decrypt_byte();
-            c.update_keys(plain);
+        let header = c.make_header(0xAB, *b"\x13\x57\x9b\xdf\x02\x46\x8a\xce\x11\x22\x33");
+        let mut data = *b"payload";
+        c.encrypt(&mut data);
+
+        let mut d = ZipCrypto::new(b"secret");
+        assert!(d.check_header(&header, 0xAB00_0000, 0, false));
+        d.decrypt(&mut data);
+        assert_eq!(&data, b"payload");
+
+        assert!(ZipCrypto::new(b"secret").check_header(&header, 0, 0xAB00, true));
+    }
+
+    #[test]
+    fn test_check_header_detailed() {
+        let header = ZipCrypto::new(b"secret").make_header(0xAB, [0x5A; 11]);
+        let check = |pwd: &[u8], crc, time, descr| {
+            ZipCrypto::new(pwd).check_header_detailed(&header, crc, time, descr)
+        };
+        assert_eq!(check(b"secret", 0xAB00_0000, 0, false), HeaderCheck::Ok);
+        assert_eq!(check(b"secret", 0, 0xAB00, true), HeaderCheck::Ok);
+        // Right password, but the other check byte is expected.
+        assert_eq!(
+            check(b"secret", 0, 0xAB00, false),
+            HeaderCheck::CrcByteMismatch
+        );
+        assert_eq!(
+            check(b"secret", 0xAB00_0000, 0, true),
+            HeaderCheck::TimeByteMismatch
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn prop_encrypt_decrypt_roundtrip(
+            data in prop::collection::vec(any::<u8>(), 0..512),
+            password in prop::collection::vec(any::<u8>(), 0..32),
+        ) {
+            let mut buf = data.clone();
+            ZipCrypto::new(&password).encrypt(&mut buf);
+            ZipCrypto::new(&password).decrypt(&mut buf);
+            prop_assert_eq!(buf, data);
         }
 
-        // Decrypt
-        let mut c = ZipCrypto::new(b"secret");
-        c.decrypt(&mut encrypted);
-        assert_eq!(&encrypted, data);
+        #[test]
+        fn prop_flipped_password_byte_changes_output(
+            data in prop::collection::vec(any::<u8>(), 16..256),
+            password in prop::collection::vec(any::<u8>(), 1..32),
+            index in any::<usize>(),
+            flip in 1u8..=255,
+        ) {
+            let mut other = password.clone();
+            other[index % password.len()] ^= flip;
+            let mut a = data.clone();
+            let mut b = data;
+            ZipCrypto::new(&password).encrypt(&mut a);
+            ZipCrypto::new(&other).encrypt(&mut b);
+            prop_assert_ne!(a, b);
+        }
+    }
+
+    #[test]
+    fn test_self_test() {
+        assert_eq!(self_test(), Ok(()));
     }
 
     #[test]
@@ -1,5 +1,6 @@
-use std::io::{Read, Write};
+use std::io::{self, Read, Write};
 
+use super::DEFAULT_BUF_SIZE;
 use crate::crypto::ZipCrypto;
 use crate::error::{AlzError, AlzResult};
 
@@ -12,10 +13,22 @@ const ALZ_EOS_MAGIC: [u8; 4] = [b'D', b'L', b'Z', 0x02];
 const BZ_STREAM_HEADER: [u8; 4] = [b'B', b'Z', b'h', b'9'];
 /// Standard bzip2 block magic (48 bits, big-endian): pi digits 0x314159265359
 const BZ_BLOCK_MAGIC: [u8; 6] = [0x31, 0x41, 0x59, 0x26, 0x53, 0x59];
-/// Standard bzip2 end-of-stream magic (48 bits): sqrt(pi) digits 0x177245385090
-const BZ_EOS_MAGIC: [u8; 6] = [0x17, 0x72, 0x45, 0x38, 0x50, 0x90];
 
-/// Reconstruct a standard bzip2 stream from ALZ-modified bzip2 data.
+/// Bits of standard stream prefix written ahead of each ALZ block payload:
+/// stream header (32), block magic (48), block CRC (32), randomised (1).
+const BZ_PREFIX_BITS: usize = 32 + 48 + 32 + 1;
+/// How far before the decoder's consumption point to look for the next
+/// ALZ header. The decoder reads ahead up to 8 bytes at a time and may
+/// hold up to 63 unread bits; in the rare case that the fake block CRC
+/// happens to match, it also reads a byte of the following header before
+/// failing.
+const HEADER_SEARCH_BITS: usize = 63 + 8 + 1;
+/// bzip2 blocks hold at most 900k bytes, so origPtr is always below this.
+const MAX_ORIG_PTR: u32 = 900_000;
+/// Reconstructed input fed to the decoder per call.
+const FEED_CHUNK: usize = 32768;
+
+/// Pull-based decoder for ALZ-modified bzip2 data.
 ///
 /// ALZ bzip2 differs from standard bzip2:
 /// - Stream header "BZh9" is absent (blockSize hardcoded to 9)
@@ -25,30 +38,77 @@ const BZ_EOS_MAGIC: [u8; 6] = [0x17, 0x72, 0x45, 0x38, 0x50, 0x90];
 /// - End-of-stream is "DLZ\x02" instead of 0x177245385090 + combined CRC
 /// - Block payload (Huffman/MTF/BWT data) is identical
 ///
-/// The reconstruction inserts a 0 randomised bit before origPtr, which
-/// shifts all subsequent bits by 1 position. This is handled by a
-/// bitstream writer.
-fn alz_to_bzip2(alz_data: &[u8]) -> AlzResult<Vec<u8>> {
-    let mut reader = BitReader::new(alz_data);
-    let mut writer = BitWriter::new();
+/// Headers are written through the bitstream, so only the first one is
+/// byte-aligned; later ones start wherever the previous block's Huffman
+/// data ended. Scanning for the "DLZ" bit pattern cannot find them
+/// reliably, because block payloads may contain the same 32 bits at any
+/// offset. Instead each block is rebuilt as a standalone standard stream
+/// (inserting the 0 randomised bit before origPtr, which shifts the
+/// payload by one bit) and handed to the decoder, which stops where the
+/// block ends: it fails the fake block CRC check right after emitting the
+/// block's data. The next header is then searched for only within the
+/// few bits around that point.
+pub struct AlzBzip2Decoder {
+    data: Vec<u8>,
+    /// Bit position of the next header, or of the next payload bit to feed
+    /// while a block is being decoded.
+    pos: usize,
+    block: Option<Block>,
+    done: bool,
+}
 
-    // Stream header.
-    writer.write_bytes(&BZ_STREAM_HEADER);
+/// Decoder state for the block currently being decoded.
+struct Block {
+    start: usize,
+    bits: BitWriter,
+    decompressor: bzip2::Decompress,
+    input: Vec<u8>,
+    input_pos: usize,
+}
 
-    loop {
-        // Read ALZ block/EOS header (4 bytes from bitstream).
+impl AlzBzip2Decoder {
+    /// Decode `data`, which must already be decrypted.
+    pub fn new(data: Vec<u8>) -> Self {
+        AlzBzip2Decoder {
+            data,
+            pos: 0,
+            block: None,
+            done: false,
+        }
+    }
+
+    /// Decompress into `out`, returning the number of bytes produced
+    /// (0 at the end of stream).
+    pub fn decode(&mut self, out: &mut [u8]) -> AlzResult<usize> {
+        if out.is_empty() {
+            return Ok(0);
+        }
+        while !self.done {
+            if self.block.is_none() {
+                self.start_block()?;
+                continue;
+            }
+            let produced = self.decode_block(out)?;
+            if produced > 0 {
+                return Ok(produced);
+            }
+        }
+        Ok(0)
+    }
+
+    /// Read the next ALZ block/EOS header (4 bytes from bitstream).
+    fn start_block(&mut self) -> AlzResult<()> {
+        let mut reader = BitReader::new(&self.data);
+        reader.seek(self.pos);
         let mut hdr = [0u8; 4];
         for b in &mut hdr {
             *b = reader.read_bits(8)? as u8;
         }
+        self.pos = reader.position();
 
         if hdr == ALZ_EOS_MAGIC {
-            // Write standard EOS magic + fake combined CRC.
-            for &b in &BZ_EOS_MAGIC {
-                writer.write_bits(b as u32, 8);
-            }
-            writer.write_bits(0, 32); // fake combined CRC
-            break;
+            self.done = true;
+            return Ok(());
         }
 
         if hdr != ALZ_BLOCK_MAGIC {
@@ -58,61 +118,121 @@ fn alz_to_bzip2(alz_data: &[u8]) -> AlzResult<Vec<u8>> {
             )));
         }
 
-        // Write standard block magic.
-        for &b in &BZ_BLOCK_MAGIC {
-            writer.write_bits(b as u32, 8);
+        let mut bits = BitWriter::new();
+        bits.write_bytes(&BZ_STREAM_HEADER);
+        bits.write_bytes(&BZ_BLOCK_MAGIC);
+        // Fake block CRC (4 bytes) and randomised = 0 (1 bit); absent in ALZ.
+        bits.write_bits(0, 32);
+        bits.write_bits(0, 1);
+
+        self.block = Some(Block {
+            start: self.pos,
+            bits,
+            decompressor: bzip2::Decompress::new(false),
+            input: Vec::new(),
+            input_pos: 0,
+        });
+        Ok(())
+    }
+
+    /// Run the block decoder once. At the end of the block, locates the
+    /// next header and clears the block state.
+    fn decode_block(&mut self, out: &mut [u8]) -> AlzResult<usize> {
+        let Some(block) = self.block.as_mut() else {
+            return Ok(0);
+        };
+
+        if block.input_pos == block.input.len() {
+            let mut reader = BitReader::new(&self.data);
+            reader.seek(self.pos);
+            while block.bits.len() < FEED_CHUNK && reader.bits_remaining() > 0 {
+                let n = reader.bits_remaining().min(8);
+                let v = reader.read_bits(n)?;
+                block.bits.write_bits(v, n);
+            }
+            if reader.bits_remaining() == 0 {
+                block.bits.flush();
+            }
+            self.pos = reader.position();
+            block.input = block.bits.take_bytes();
+            block.input_pos = 0;
+            if block.input.is_empty() {
+                return Err(AlzError::Bzip2Failed("unexpected end of bzip2 data".into()));
+            }
         }
 
-        // Write fake block CRC (4 bytes).
-        writer.write_bits(0, 32);
-
-        // Write randomised = 0 (1 bit). This is absent in ALZ.
-        writer.write_bits(0, 1);
-
-        // Copy remaining bits until next block header.
-        // We can't know the block boundary without decoding, so for
-        // each block we copy bits one at a time until we peek "DLZ"
-        // or run out of data.
-        //
-        // Block headers use 8-bit reads from the bitstream, so we
-        // need to detect the DLZ pattern at the current bit position.
-        // We peek 32 bits ahead to check.
-        loop {
-            if reader.bits_remaining() < 32 {
-                // Copy remaining bits.
-                while reader.bits_remaining() > 0 {
-                    let n = reader.bits_remaining().min(8);
-                    let v = reader.read_bits(n)?;
-                    writer.write_bits(v, n);
+        let decompressor = &mut block.decompressor;
+        let before_in = decompressor.total_in();
+        let before_out = decompressor.total_out();
+
+        let result = decompressor.decompress(&block.input[block.input_pos..], out);
+
+        let consumed = (decompressor.total_in() - before_in) as usize;
+        let produced = (decompressor.total_out() - before_out) as usize;
+        block.input_pos += consumed;
+
+        match result {
+            // End of block: the fake CRC fails the check (or, if it happens
+            // to match, the following ALZ header fails the magic check).
+            // Corrupt payload also lands here; find_header tells them apart.
+            Err(_) | Ok(bzip2::Status::StreamEnd) => {
+                let consumed_bits = decompressor.total_in() as usize * 8;
+                if consumed_bits < BZ_PREFIX_BITS {
+                    return Err(AlzError::Bzip2Failed("bzip2 decompression failed".into()));
                 }
-                break;
+                let end = block.start + (consumed_bits - BZ_PREFIX_BITS);
+                self.pos = find_header(&self.data, block.start, end).ok_or_else(|| {
+                    AlzError::Bzip2Failed("corrupt block: no header after block data".into())
+                })?;
+                self.block = None;
             }
-
-            // Peek next 32 bits to check for ALZ header.
-            let peek = reader.peek_bits(32)?;
-            let peek_bytes = peek.to_be_bytes();
-            if peek_bytes == ALZ_BLOCK_MAGIC || peek_bytes == ALZ_EOS_MAGIC {
-                break; // Don't consume; outer loop reads the header.
+            Ok(_) => {
+                if consumed == 0 && produced == 0 && block.input_pos < block.input.len() {
+                    return Err(AlzError::Bzip2Failed(
+                        "bzip2 decompression made no progress".into(),
+                    ));
+                }
             }
-
-            // Not a header; copy 1 bit.
-            let bit = reader.read_bits(1)?;
-            writer.write_bits(bit, 1);
         }
+        Ok(produced)
     }
+}
 
-    writer.flush();
-    Ok(writer.into_bytes())
+impl Read for AlzBzip2Decoder {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.decode(buf).map_err(|e| match e {
+            AlzError::Io(e) => e,
+            e => io::Error::new(io::ErrorKind::InvalidData, e),
+        })
+    }
 }
 
-/// Extract ALZ-modified bzip2 data.
-/// Returns the CRC32 of the decompressed data.
-pub fn extract_bzip2<R: Read, W: Write>(
+/// Find the ALZ block/EOS header that follows a block decoded up to bit
+/// `end`, searching at most [`HEADER_SEARCH_BITS`] bits back but not
+/// before the block payload at `start`. A block header only counts if its
+/// origPtr is in range, which rules out most chance matches in the
+/// surrounding payload bits.
+fn find_header(data: &[u8], start: usize, end: usize) -> Option<usize> {
+    let lo = end.saturating_sub(HEADER_SEARCH_BITS).max(start);
+    let mut reader = BitReader::new(data);
+    (lo..=end).find(|&pos| {
+        reader.seek(pos);
+        match reader.read_bits(32).map(u32::to_be_bytes) {
+            Ok(b) if b == ALZ_EOS_MAGIC => true,
+            Ok(b) if b == ALZ_BLOCK_MAGIC => reader
+                .peek_bits(24)
+                .is_ok_and(|orig_ptr| orig_ptr < MAX_ORIG_PTR),
+            _ => false,
+        }
+    })
+}
+
+/// Read and decrypt the whole compressed payload of a bzip2 entry.
+pub(crate) fn read_compressed<R: Read>(
     reader: &mut R,
-    writer: &mut W,
     compressed_size: u64,
-    mut crypto: Option<&mut ZipCrypto>,
-) -> AlzResult<u32> {
+    crypto: Option<&mut ZipCrypto>,
+) -> AlzResult<Vec<u8>> {
     // ALZ bzip2 blocks are at most 900KB uncompressed; reject absurdly large sizes.
     const MAX_BZ2_COMPRESSED: u64 = 512 * 1024 * 1024;
     if compressed_size > MAX_BZ2_COMPRESSED {
@@ -121,62 +241,80 @@ pub fn extract_bzip2<R: Read, W: Write>(
         )));
     }
 
-    // Read all compressed data.
-    let mut alz_data = vec![0u8; compressed_size as usize];
-    reader.read_exact(&mut alz_data)?;
-    if let Some(ref mut c) = crypto {
+    // Reserve fallibly so a crafted size reports an error instead of
+    // aborting the process on allocation failure.
+    let alloc_size: usize = compressed_size.try_into().map_err(|_| {
+        AlzError::Bzip2Failed(format!(
+            "compressed size {compressed_size} exceeds address space"
+        ))
+    })?;
+    let mut alz_data = Vec::new();
+    alz_data
+        .try_reserve_exact(alloc_size)
+        .map_err(|_| AlzError::Bzip2Failed(format!("can't allocate {compressed_size} bytes")))?;
+    reader.take(compressed_size).read_to_end(&mut alz_data)?;
+    if alz_data.len() != alloc_size {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+    }
+    if let Some(c) = crypto {
         c.decrypt(&mut alz_data);
     }
+    Ok(alz_data)
+}
 
-    // Reconstruct standard bzip2 stream.
-    let bz_data = alz_to_bzip2(&alz_data)?;
+/// Extract ALZ-modified bzip2 data.
+/// Returns the CRC32 of the decompressed data.
+pub fn extract_bzip2<R: Read, W: Write>(
+    reader: &mut R,
+    writer: &mut W,
+    compressed_size: u64,
+    crypto: Option<&mut ZipCrypto>,
+) -> AlzResult<u32> {
+    extract_bzip2_buffered(reader, writer, compressed_size, crypto, DEFAULT_BUF_SIZE)
+}
 
-    // Decompress using standard bzip2.
-    let mut decompressor = bzip2::Decompress::new(false);
-    let mut input_pos = 0;
+/// [`extract_bzip2`] with a `buf_size`-byte output buffer.
+pub fn extract_bzip2_buffered<R: Read, W: Write>(
+    reader: &mut R,
+    writer: &mut W,
+    compressed_size: u64,
+    crypto: Option<&mut ZipCrypto>,
+    buf_size: usize,
+) -> AlzResult<u32> {
+    let alz_data = read_compressed(reader, compressed_size, crypto)?;
+    let mut decoder = AlzBzip2Decoder::new(alz_data);
     let mut hasher = crc32fast::Hasher::new();
-    let mut tmp = [0u8; 32768];
+    let mut tmp = vec![0u8; buf_size.max(1)];
 
     loop {
-        let before_in = decompressor.total_in();
-        let before_out = decompressor.total_out();
-
-        let result = decompressor.decompress(&bz_data[input_pos..], &mut tmp);
-
-        let consumed = (decompressor.total_in() - before_in) as usize;
-        let produced = (decompressor.total_out() - before_out) as usize;
-        input_pos += consumed;
-
-        if produced > 0 {
-            hasher.update(&tmp[..produced]);
-            writer
-                .write_all(&tmp[..produced])
-                .map_err(AlzError::CantOpenDestFile)?;
-        }
-
-        match result {
-            Ok(bzip2::Status::Ok) => continue,
-            Ok(bzip2::Status::MemNeeded) => {
-                if consumed == 0 && produced == 0 {
-                    break; // No progress.
-                }
-            }
-            Ok(bzip2::Status::FlushOk | bzip2::Status::FinishOk) => continue,
-            Ok(bzip2::Status::StreamEnd) => break,
-            Ok(bzip2::Status::RunOk) => continue,
-            Err(_) => {
-                // CRC error from fake CRCs is expected; if we got data, accept it.
-                if decompressor.total_out() > 0 {
-                    break;
-                }
-                return Err(AlzError::Bzip2Failed("bzip2 decompression failed".into()));
-            }
+        let n = decoder.decode(&mut tmp)?;
+        if n == 0 {
+            break;
         }
+        hasher.update(&tmp[..n]);
+        writer
+            .write_all(&tmp[..n])
+            .map_err(AlzError::CantOpenDestFile)?;
     }
 
     Ok(hasher.finalize())
 }
 
+/// Decompress a complete, unencrypted ALZ bzip2 payload (from the first
+/// "DLZ\x01" header through "DLZ\x02") into memory.
+pub fn decompress_alz_block(alz_data: &[u8]) -> AlzResult<Vec<u8>> {
+    let mut decoder = AlzBzip2Decoder::new(alz_data.to_vec());
+    let mut out = Vec::new();
+    let mut tmp = vec![0u8; DEFAULT_BUF_SIZE];
+    loop {
+        let n = decoder.decode(&mut tmp)?;
+        if n == 0 {
+            return Ok(out);
+        }
+        out.extend_from_slice(&tmp[..n]);
+    }
+}
+
 /// MSB-first bit reader.
 struct BitReader<'a> {
     data: &'a [u8],
@@ -193,6 +331,17 @@ impl<'a> BitReader<'a> {
         }
     }
 
+    /// Current position in bits from the start of the data.
+    fn position(&self) -> usize {
+        self.byte_pos * 8 + self.bit_pos as usize
+    }
+
+    /// Move to an absolute bit position.
+    fn seek(&mut self, pos: usize) {
+        self.byte_pos = pos / 8;
+        self.bit_pos = (pos % 8) as u8;
+    }
+
     fn bits_remaining(&self) -> usize {
         if self.byte_pos >= self.data.len() {
             return 0;
@@ -206,37 +355,35 @@ impl<'a> BitReader<'a> {
         }
         let mut val: u32 = 0;
         for _ in 0..n {
-            val = (val << 1) | self.read_bit() as u32;
+            val = (val << 1) | self.read_bit()? as u32;
         }
         Ok(val)
     }
 
-    fn read_bit(&mut self) -> u8 {
-        let bit = (self.data[self.byte_pos] >> (7 - self.bit_pos)) & 1;
+    fn read_bit(&mut self) -> AlzResult<u8> {
+        let byte = self
+            .data
+            .get(self.byte_pos)
+            .ok_or_else(|| AlzError::Bzip2Failed("unexpected end of bzip2 data".into()))?;
+        let bit = (byte >> (7 - self.bit_pos)) & 1;
         self.bit_pos += 1;
         if self.bit_pos == 8 {
             self.bit_pos = 0;
             self.byte_pos += 1;
         }
-        bit
+        Ok(bit)
     }
 
     fn peek_bits(&self, n: usize) -> AlzResult<u32> {
         if n > 32 || self.bits_remaining() < n {
             return Err(AlzError::Bzip2Failed("unexpected end of bzip2 data".into()));
         }
-        let mut byte_pos = self.byte_pos;
-        let mut bit_pos = self.bit_pos;
-        let mut val: u32 = 0;
-        for _ in 0..n {
-            val = (val << 1) | ((self.data[byte_pos] >> (7 - bit_pos)) & 1) as u32;
-            bit_pos += 1;
-            if bit_pos == 8 {
-                bit_pos = 0;
-                byte_pos += 1;
-            }
-        }
-        Ok(val)
+        let mut probe = BitReader {
+            data: self.data,
+            byte_pos: self.byte_pos,
+            bit_pos: self.bit_pos,
+        };
+        probe.read_bits(n)
     }
 }
 
@@ -275,6 +422,16 @@ impl BitWriter {
         }
     }
 
+    /// Number of completed bytes not yet taken.
+    fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Take the completed bytes, keeping any partial byte.
+    fn take_bytes(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.data)
+    }
+
     fn flush(&mut self) {
         if self.bit_pos > 0 {
             self.data.push(self.current);
@@ -282,8 +439,134 @@ impl BitWriter {
             self.bit_pos = 0;
         }
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Convert a standard bzip2 stream to ALZ form: drop the stream header,
+    /// block CRCs, randomised bits and combined CRC, and swap the magics.
+    fn std_to_alz(std: &[u8]) -> Vec<u8> {
+        let bits: Vec<u8> = std
+            .iter()
+            .flat_map(|b| (0..8).rev().map(move |i| (b >> i) & 1))
+            .collect();
+        let mut blocks = Vec::new();
+        let mut eos = 0;
+        let mut window = 0u64;
+        for (i, &bit) in bits.iter().enumerate().skip(32) {
+            window = ((window << 1) | bit as u64) & 0xffff_ffff_ffff;
+            if i >= 32 + 47 {
+                let start = i - 47;
+                if window == 0x314159265359 {
+                    blocks.push(start);
+                } else if window == 0x177245385090 {
+                    eos = start;
+                    break;
+                }
+            }
+        }
+
+        let mut w = BitWriter::new();
+        for (k, &start) in blocks.iter().enumerate() {
+            let end = blocks.get(k + 1).copied().unwrap_or(eos);
+            w.write_bytes(&ALZ_BLOCK_MAGIC);
+            for &bit in &bits[start + 48 + 32 + 1..end] {
+                w.write_bits(bit as u32, 1);
+            }
+        }
+        w.write_bytes(&ALZ_EOS_MAGIC);
+        w.flush();
+        w.take_bytes()
+    }
+
+    fn compress_alz(data: &[u8]) -> Vec<u8> {
+        let mut enc = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::best());
+        enc.write_all(data).unwrap();
+        std_to_alz(&enc.finish().unwrap())
+    }
+
+    fn decode(alz: &[u8]) -> AlzResult<Vec<u8>> {
+        let mut out = Vec::new();
+        extract_bzip2(&mut &alz[..], &mut out, alz.len() as u64, None)?;
+        Ok(out)
+    }
+
+    /// Pseudo-random bytes drawn from `alphabet`.
+    fn sample(alphabet: &[u8], len: usize) -> Vec<u8> {
+        let mut state = 0x2545f491u32;
+        (0..len)
+            .map(|_| {
+                state = state.wrapping_mul(1103515245).wrapping_add(12345);
+                alphabet[(state >> 16) as usize % alphabet.len()]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_multi_block() {
+        // More than one 900k block; every block after the first starts at
+        // an arbitrary bit offset.
+        let data = sample(b"abcdefghij \n", 1_000_000);
+        let alz = compress_alz(&data);
+        assert_eq!(decode(&alz).unwrap(), data);
+    }
+
+    #[test]
+    fn test_header_pattern_in_payload() {
+        // The block's symbol bitmap (starting 72 bits in, after the header,
+        // origPtr and inUse16) has one bit per byte value, so choosing which
+        // bytes occur embeds any bit pattern at any offset.
+        for magic in [ALZ_BLOCK_MAGIC, ALZ_EOS_MAGIC] {
+            let pattern = u32::from_be_bytes(magic);
+            for offset in 0..8 {
+                let mut present = [true; 256];
+                for i in 0..32 {
+                    present[16 + offset + i] = pattern & (1 << (31 - i)) != 0;
+                }
+                let alphabet: Vec<u8> = (0..=255u8).filter(|&b| present[b as usize]).collect();
+                let data = sample(&alphabet, 4096);
+                let alz = compress_alz(&data);
+
+                let mut reader = BitReader::new(&alz);
+                reader.seek(72 + 16 + offset);
+                assert_eq!(reader.peek_bits(32).unwrap(), pattern);
+                assert_eq!(decode(&alz).unwrap(), data, "offset {offset}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_truncated() {
+        let alz = compress_alz(b"hello hello hello");
+        assert!(decode(&alz[..alz.len() / 2]).is_err());
+    }
+
+    #[test]
+    fn test_bit_reader_at_end() {
+        let data = [0b1010_0000, 0xff];
+        let mut reader = BitReader::new(&data);
+        assert_eq!(reader.read_bits(3).unwrap(), 0b101);
+        assert_eq!(reader.read_bits(13).unwrap(), 0x00ff);
+        assert_eq!(reader.bits_remaining(), 0);
+        assert!(reader.read_bit().is_err());
+        assert!(reader.read_bits(1).is_err());
+        assert!(reader.peek_bits(1).is_err());
+
+        // Positions past the end, including mid-byte ones.
+        for pos in [16, 17, 23, 100] {
+            reader.seek(pos);
+            assert_eq!(reader.bits_remaining(), 0);
+            assert!(reader.read_bit().is_err());
+        }
+        reader.seek(15);
+        assert_eq!(reader.read_bit().unwrap(), 1);
+        assert!(reader.read_bit().is_err());
+    }
 
-    fn into_bytes(self) -> Vec<u8> {
-        self.data
+    #[test]
+    fn test_bad_header() {
+        assert!(decode(b"XLZ\x01\x00\x00\x00\x00").is_err());
     }
 }
@@ -2,42 +2,59 @@ use std::io::{Read, Write};
 
 use flate2::{Decompress, FlushDecompress, Status};
 
+use super::DEFAULT_BUF_SIZE;
 use crate::crypto::ZipCrypto;
 use crate::error::{AlzError, AlzResult};
 
-const IN_BUF_SIZE: usize = 32768;
-const OUT_BUF_SIZE: usize = 32768;
-
 /// Extract DEFLATE compressed data (raw deflate, no zlib/gzip header).
 /// Returns the CRC32 of the decompressed data.
 pub fn extract_deflate<R: Read, W: Write>(
+    reader: &mut R,
+    writer: &mut W,
+    compressed_size: u64,
+    crypto: Option<&mut ZipCrypto>,
+) -> AlzResult<u32> {
+    extract_deflate_buffered(reader, writer, compressed_size, crypto, DEFAULT_BUF_SIZE)
+}
+
+/// [`extract_deflate`] with `buf_size`-byte input and output buffers.
+pub fn extract_deflate_buffered<R: Read, W: Write>(
     reader: &mut R,
     writer: &mut W,
     compressed_size: u64,
     mut crypto: Option<&mut ZipCrypto>,
+    buf_size: usize,
 ) -> AlzResult<u32> {
     let mut hasher = crc32fast::Hasher::new();
-    let mut in_buf = [0u8; IN_BUF_SIZE];
-    let mut out_buf = [0u8; OUT_BUF_SIZE];
+    let buf_size = buf_size.max(1);
+    let mut in_buf = vec![0u8; buf_size];
+    let mut out_buf = vec![0u8; buf_size];
     let mut decompressor = Decompress::new(false); // raw deflate (no header)
     let mut remaining = compressed_size;
     let mut in_avail = 0usize; // unconsumed bytes at front of in_buf
 
     loop {
-        // Refill input buffer if we have no pending data and there's more to read.
-        if in_avail == 0 && remaining > 0 {
-            let to_read = (remaining as usize).min(IN_BUF_SIZE);
-            reader.read_exact(&mut in_buf[..to_read])?;
+        // Top up the input buffer whenever there is room and more to read,
+        // so a decoder waiting on input (BufError) always gets more.
+        let mut refilled = false;
+        if in_avail < buf_size && remaining > 0 {
+            // Clamp in u64 first: `remaining as usize` truncates on 32-bit targets.
+            let to_read = remaining.min((buf_size - in_avail) as u64) as usize;
+            let chunk = &mut in_buf[in_avail..in_avail + to_read];
+            reader.read_exact(chunk)?;
             if let Some(ref mut c) = crypto {
-                c.decrypt(&mut in_buf[..to_read]);
+                c.decrypt(chunk);
             }
             remaining -= to_read as u64;
-            in_avail = to_read;
+            in_avail += to_read;
+            refilled = true;
         }
 
         let before_in = decompressor.total_in();
         let before_out = decompressor.total_out();
 
+        // BufError is reported as a status, not an error: it only means no
+        // progress was possible with the buffers given. Errors are corrupt data.
         let status = decompressor
             .decompress(&in_buf[..in_avail], &mut out_buf, FlushDecompress::Sync)
             .map_err(|e| AlzError::InflateFailed(e.to_string()))?;
@@ -62,8 +79,17 @@ pub fn extract_deflate<R: Read, W: Write>(
             break;
         }
 
-        if in_avail == 0 && remaining == 0 && produced == 0 {
-            break;
+        if consumed == 0 && produced == 0 {
+            if remaining == 0 {
+                // Input exhausted without an end-of-stream marker; the CRC
+                // check reports the truncation.
+                break;
+            }
+            if !refilled {
+                return Err(AlzError::InflateFailed(
+                    "deflate stream made no progress".into(),
+                ));
+            }
         }
     }
 
@@ -1,3 +1,42 @@
+use std::io::Read;
+
+use crate::archive::CompressionMethod;
+use crate::crypto::{DecryptReader, ZipCrypto};
+use crate::error::{AlzError, AlzResult};
+
 pub mod bzip2;
 pub mod deflate;
 pub mod raw;
+
+/// Default size of the copy and (de)compression buffers.
+///
+/// Inflating a 256 MiB deflate entry to tmpfs ran at about 200-210 MB/s
+/// with 32 KiB, 256 KiB and 1 MiB buffers alike, so the default stays small.
+pub const DEFAULT_BUF_SIZE: usize = 32768;
+
+/// Wrap `inner` in a reader yielding the decompressed contents of an entry.
+///
+/// `inner` must be positioned at the start of the entry data; at most `size`
+/// bytes are read from it. `crypto` must already have consumed the
+/// encryption header. Bzip2 entries are read and decrypted in full before
+/// this returns; Store and Deflate entries are decoded as they are read.
+/// The returned reader does not check the CRC.
+pub fn reader_for<'a, R: Read + 'a>(
+    method: CompressionMethod,
+    inner: R,
+    size: u64,
+    mut crypto: Option<ZipCrypto>,
+) -> AlzResult<Box<dyn Read + 'a>> {
+    match method {
+        CompressionMethod::Store => Ok(Box::new(DecryptReader::new(inner.take(size), crypto))),
+        CompressionMethod::Deflate => Ok(Box::new(flate2::read::DeflateDecoder::new(
+            DecryptReader::new(inner.take(size), crypto),
+        ))),
+        CompressionMethod::Bzip2 => {
+            let mut inner = inner;
+            let data = bzip2::read_compressed(&mut inner, size, crypto.as_mut())?;
+            Ok(Box::new(bzip2::AlzBzip2Decoder::new(data)))
+        }
+        CompressionMethod::Unknown(n) => Err(AlzError::UnknownCompressionMethod(n)),
+    }
+}
@@ -1,24 +1,36 @@
 use std::io::{Read, Write};
 
+use super::DEFAULT_BUF_SIZE;
 use crate::crypto::ZipCrypto;
 use crate::error::{AlzError, AlzResult};
 
-const BUF_SIZE: usize = 32768;
-
 /// Extract uncompressed data, optionally decrypting.
 /// Returns the CRC32 of the extracted data.
 pub fn extract_raw<R: Read, W: Write>(
+    reader: &mut R,
+    writer: &mut W,
+    size: u64,
+    crypto: Option<&mut ZipCrypto>,
+) -> AlzResult<u32> {
+    extract_raw_buffered(reader, writer, size, crypto, DEFAULT_BUF_SIZE)
+}
+
+/// [`extract_raw`] copying through a `buf_size`-byte buffer.
+pub fn extract_raw_buffered<R: Read, W: Write>(
     reader: &mut R,
     writer: &mut W,
     size: u64,
     mut crypto: Option<&mut ZipCrypto>,
+    buf_size: usize,
 ) -> AlzResult<u32> {
     let mut hasher = crc32fast::Hasher::new();
-    let mut buf = [0u8; BUF_SIZE];
+    let buf_size = buf_size.max(1);
+    let mut buf = vec![0u8; buf_size];
     let mut remaining = size;
 
     while remaining > 0 {
-        let to_read = (remaining as usize).min(BUF_SIZE);
+        // Clamp in u64 first: `remaining as usize` truncates on 32-bit targets.
+        let to_read = remaining.min(buf_size as u64) as usize;
         reader.read_exact(&mut buf[..to_read])?;
 
         let data = &mut buf[..to_read];
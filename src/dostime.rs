@@ -1,4 +1,53 @@
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
+
+/// How the wall-clock time in a DOS timestamp, which records no time zone,
+/// maps to an instant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DosTimeZone {
+    /// Treat stored times as UTC, as unalz always has.
+    #[default]
+    Utc,
+    /// Stored times are local time at this many seconds east of UTC, e.g.
+    /// `9 * 3600` for archives made by ALZip in Korea. The offset is fixed,
+    /// so daylight saving time is not applied.
+    Offset(i32),
+    /// Stored times are in this machine's local time zone. The offset,
+    /// including daylight saving time, is looked up for each timestamp.
+    Local,
+}
+
+impl DosTimeZone {
+    /// Parse `utc`, `z`, `local` or an offset such as `+09:00`, `-0530` or
+    /// `+9`.
+    pub fn parse(s: &str) -> Option<Self> {
+        if s.eq_ignore_ascii_case("utc") || s.eq_ignore_ascii_case("z") {
+            return Some(Self::Utc);
+        }
+        if s.eq_ignore_ascii_case("local") {
+            return Some(Self::Local);
+        }
+        let (sign, rest) = match s.as_bytes().first()? {
+            b'+' => (1, &s[1..]),
+            b'-' => (-1, &s[1..]),
+            _ => return None,
+        };
+        let (hours, minutes) = match rest.split_once(':') {
+            Some((h, m)) => (h, m),
+            None if rest.len() == 4 => rest.split_at(2),
+            None => (rest, "0"),
+        };
+        let all_digits =
+            |t: &str| !t.is_empty() && t.len() <= 2 && t.bytes().all(|b| b.is_ascii_digit());
+        if !all_digits(hours) || !all_digits(minutes) {
+            return None;
+        }
+        let (hours, minutes): (i32, i32) = (hours.parse().ok()?, minutes.parse().ok()?);
+        if hours > 14 || minutes > 59 {
+            return None;
+        }
+        Some(Self::Offset(sign * (hours * 3600 + minutes * 60)))
+    }
+}
 
 /// Convert DOS date/time (as stored in ALZ headers) to SystemTime.
 /// DOS time format:
@@ -8,7 +57,18 @@ use std::time::SystemTime;
 ///   bits 16-20: day (1-31)
 ///   bits 21-24: month (1-12)
 ///   bits 25-31: year offset from 1980
+///
+/// The stored time is taken as UTC; see [`dos_datetime_to_systime_in`] for
+/// archives written in another time zone or in local time. The year field
+/// limits dates to
+/// 1980-2107. Returns `None` for impossible dates and times, such as
+/// month 0, April 31 or hour 24.
 pub fn dos_datetime_to_systime(dostime: u32) -> Option<SystemTime> {
+    dos_datetime_to_systime_in(dostime, DosTimeZone::Utc)
+}
+
+/// [`dos_datetime_to_systime`] for a stored time in `zone`.
+pub fn dos_datetime_to_systime_in(dostime: u32, zone: DosTimeZone) -> Option<SystemTime> {
     let sec = (dostime & 0x1f) << 1;
     let min = (dostime >> 5) & 0x3f;
     let hour = (dostime >> 11) & 0x1f;
@@ -16,7 +76,12 @@ pub fn dos_datetime_to_systime(dostime: u32) -> Option<SystemTime> {
     let month = (dostime >> 21) & 0x0f;
     let year = ((dostime >> 25) & 0x7f) + 1980;
 
-    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+    if !(1..=12).contains(&month)
+        || !(1..=days_in_month(year, month)).contains(&day)
+        || hour > 23
+        || min > 59
+        || sec > 59
+    {
         return None;
     }
 
@@ -24,11 +89,41 @@ pub fn dos_datetime_to_systime(dostime: u32) -> Option<SystemTime> {
     // Days from epoch (1970-01-01) to the given date.
     let days = days_from_epoch(year, month, day);
     let secs = days as u64 * 86400 + hour as u64 * 3600 + min as u64 * 60 + sec as u64;
+    let offset = match zone {
+        DosTimeZone::Utc => 0,
+        DosTimeZone::Offset(offset) => offset,
+        DosTimeZone::Local => local_offset(secs)?,
+    };
+    let secs = secs.checked_add_signed(-i64::from(offset))?;
 
-    Some(SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(secs))
+    Some(SystemTime::UNIX_EPOCH + Duration::from_secs(secs))
 }
 
-/// Format DOS datetime for display (YYYY-MM-DD HH:MM:SS).
+/// Offset from UTC, in seconds, of the local wall-clock time `local_secs`
+/// (seconds since 1970-01-01 00:00 local time). Ambiguous times, repeated
+/// when the clocks go back, take the earlier instant; times skipped when
+/// they go forward are read an hour later, as `mktime` does.
+fn local_offset(local_secs: u64) -> Option<i32> {
+    use chrono::{DateTime, Local, Offset, TimeDelta, TimeZone};
+
+    let naive = DateTime::from_timestamp(i64::try_from(local_secs).ok()?, 0)?.naive_utc();
+    let time = Local.from_local_datetime(&naive).earliest().or_else(|| {
+        Local
+            .from_local_datetime(&(naive + TimeDelta::hours(1)))
+            .earliest()
+    })?;
+    Some(time.offset().fix().local_minus_utc())
+}
+
+/// Whether `dostime` is a real date and time; see
+/// [`dos_datetime_to_systime`].
+pub fn is_valid_dos_datetime(dostime: u32) -> bool {
+    dos_datetime_to_systime(dostime).is_some()
+}
+
+/// Format DOS datetime for display (YYYY-MM-DD HH:MM:SS). The stored
+/// fields are shown as they are; an impossible date or time, such as
+/// 2021-02-30, gets a trailing `?`.
 pub fn dos_datetime_to_string(dostime: u32) -> String {
     let sec = (dostime & 0x1f) << 1;
     let min = (dostime >> 5) & 0x3f;
@@ -37,7 +132,23 @@ pub fn dos_datetime_to_string(dostime: u32) -> String {
     let month = (dostime >> 21) & 0x0f;
     let year = ((dostime >> 25) & 0x7f) + 1980;
 
-    format!("{year:04}-{month:02}-{day:02} {hour:02}:{min:02}:{sec:02}")
+    let flag = if is_valid_dos_datetime(dostime) {
+        ""
+    } else {
+        "?"
+    };
+    format!("{year:04}-{month:02}-{day:02} {hour:02}:{min:02}:{sec:02}{flag}")
+}
+
+fn days_in_month(year: u32, month: u32) -> u32 {
+    match month {
+        4 | 6 | 9 | 11 => 30,
+        2 if year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400)) => {
+            29
+        }
+        2 => 28,
+        _ => 31,
+    }
 }
 
 fn days_from_epoch(year: u32, month: u32, day: u32) -> i64 {
@@ -59,6 +170,24 @@ fn days_from_epoch(year: u32, month: u32, day: u32) -> i64 {
     era * 146097 + doe as i64 - 719468
 }
 
+/// Known-answer checks for the DOS time conversions, for runtime self-tests.
+pub(crate) fn self_test() -> Result<(), String> {
+    let s = dos_datetime_to_string(0x4E8C2209);
+    if s != "2019-04-12 04:16:18" {
+        return Err(format!("DOS time 0x4E8C2209 formatted as {s}"));
+    }
+    let secs = dos_datetime_to_systime(0x4E8C2209)
+        .and_then(|t| t.duration_since(SystemTime::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs());
+    if secs != Some(1555042578) {
+        return Err(format!("DOS time 0x4E8C2209 converted to {secs:?}"));
+    }
+    if dos_datetime_to_systime(0).is_some() {
+        return Err("DOS time 0 accepted as valid".into());
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -79,9 +208,70 @@ mod tests {
         assert_eq!(secs, 1555042578);
     }
 
+    #[test]
+    fn test_time_zone() {
+        let utc = dos_datetime_to_systime(0x4E8C2209).unwrap();
+        let kst = dos_datetime_to_systime_in(0x4E8C2209, DosTimeZone::Offset(9 * 3600)).unwrap();
+        assert_eq!(utc.duration_since(kst).unwrap().as_secs(), 9 * 3600);
+        let west = dos_datetime_to_systime_in(0x4E8C2209, DosTimeZone::Offset(-5 * 3600)).unwrap();
+        assert_eq!(west.duration_since(utc).unwrap().as_secs(), 5 * 3600);
+
+        assert_eq!(DosTimeZone::parse("UTC"), Some(DosTimeZone::Utc));
+        assert_eq!(
+            DosTimeZone::parse("+09:00"),
+            Some(DosTimeZone::Offset(32400))
+        );
+        assert_eq!(DosTimeZone::parse("+9"), Some(DosTimeZone::Offset(32400)));
+        assert_eq!(
+            DosTimeZone::parse("-0530"),
+            Some(DosTimeZone::Offset(-19800))
+        );
+        assert_eq!(DosTimeZone::parse("09:00"), None);
+        assert_eq!(DosTimeZone::parse("+25"), None);
+        assert_eq!(DosTimeZone::parse("+1:2:3"), None);
+    }
+
+    #[test]
+    fn test_local_time_zone() {
+        use chrono::{Local, NaiveDate, TimeZone};
+
+        assert_eq!(DosTimeZone::parse("Local"), Some(DosTimeZone::Local));
+        // 2019-04-12 04:16:18 on this machine's clock.
+        let local = dos_datetime_to_systime_in(0x4E8C2209, DosTimeZone::Local).unwrap();
+        let naive = NaiveDate::from_ymd_opt(2019, 4, 12)
+            .unwrap()
+            .and_hms_opt(4, 16, 18)
+            .unwrap();
+        let expected = Local.from_local_datetime(&naive).earliest().unwrap();
+        assert_eq!(local, SystemTime::from(expected));
+    }
+
+    #[test]
+    fn test_self_test() {
+        assert_eq!(self_test(), Ok(()));
+    }
+
     #[test]
     fn test_invalid_date() {
         // Month 0 is invalid
         assert!(dos_datetime_to_systime(0).is_none());
+        // 2019-04-12 24:16:18 and 2019-04-12 04:60:18.
+        assert!(dos_datetime_to_systime(0x4E8CC209).is_none());
+        assert!(dos_datetime_to_systime(0x4E8C2789).is_none());
+        // 2020-02-29 exists, 2019-02-29 does not.
+        assert!(dos_datetime_to_systime(0x505D0000).is_some());
+        assert!(dos_datetime_to_systime(0x4E5D0000).is_none());
+    }
+
+    #[test]
+    fn test_day_past_end_of_month() {
+        // 2021-02-30 12:00:00 and 2021-04-31 00:00:00.
+        assert!(dos_datetime_to_systime(0x525E6000).is_none());
+        assert!(dos_datetime_to_systime(0x529F0000).is_none());
+        assert_eq!(dos_datetime_to_string(0x525E6000), "2021-02-30 12:00:00?");
+        assert_eq!(dos_datetime_to_string(0x529F0000), "2021-04-31 00:00:00?");
+        // 2021-04-30 is fine.
+        assert!(is_valid_dos_datetime(0x529E0000));
+        assert_eq!(dos_datetime_to_string(0x529E0000), "2021-04-30 00:00:00");
     }
 }
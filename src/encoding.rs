@@ -1,3 +1,147 @@
+use encoding_rs::{EUC_KR, Encoding, SHIFT_JIS, UTF_8};
+
+/// How entry names are decoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NameDecoding {
+    /// Guess per name with [`detect`].
+    #[default]
+    Auto,
+    /// Always decode as CP949, the encoding ALZip writes.
+    Cp949,
+    /// Take names that happen to be valid UTF-8 as UTF-8, and decode the
+    /// rest as CP949. Short CP949 names can be valid UTF-8 by accident.
+    Utf8First,
+    /// Decode with another encoding, e.g. for archives written on a
+    /// Japanese or Chinese system.
+    Encoding(&'static Encoding),
+}
+
+impl NameDecoding {
+    /// Look up a codepage by name: `cp949` or any WHATWG encoding label
+    /// (`shift_jis`, `gbk`, `utf-8`, ...), case-insensitively.
+    pub fn from_label(label: &str) -> Option<Self> {
+        if label.eq_ignore_ascii_case("cp949") || label.eq_ignore_ascii_case("uhc") {
+            return Some(Self::Cp949);
+        }
+        let encoding = Encoding::for_label(label.as_bytes())?;
+        Some(if encoding == EUC_KR {
+            Self::Cp949
+        } else {
+            Self::Encoding(encoding)
+        })
+    }
+
+    /// Decode a raw entry name.
+    pub fn decode(self, bytes: &[u8]) -> String {
+        match self {
+            Self::Auto => detect(bytes)
+                .decode_without_bom_handling(bytes)
+                .0
+                .into_owned(),
+            Self::Cp949 => EUC_KR.decode(bytes).0.into_owned(),
+            Self::Utf8First => cp949_to_utf8(bytes),
+            Self::Encoding(encoding) => encoding.decode_without_bom_handling(bytes).0.into_owned(),
+        }
+    }
+}
+
+/// Unicode normalization applied to decoded entry names. ALZip writes
+/// precomposed (NFC) Hangul, while macOS file systems store names
+/// decomposed (NFD), so the same name can arrive in either form.
+#[cfg(feature = "normalize")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NameNormalization {
+    /// Keep names exactly as decoded.
+    #[default]
+    None,
+    /// Compose, e.g. `ㅎ` + `ㅏ` + `ㄴ` into `한`.
+    Nfc,
+    /// Decompose, the form macOS uses.
+    Nfd,
+}
+
+#[cfg(feature = "normalize")]
+impl NameNormalization {
+    /// Look up a form by name: `none`, `nfc` or `nfd`, case-insensitively.
+    pub fn from_label(label: &str) -> Option<Self> {
+        match label.to_ascii_lowercase().as_str() {
+            "none" => Some(Self::None),
+            "nfc" => Some(Self::Nfc),
+            "nfd" => Some(Self::Nfd),
+            _ => None,
+        }
+    }
+
+    /// Normalize a decoded name.
+    pub fn apply(self, name: &str) -> String {
+        use unicode_normalization::UnicodeNormalization;
+
+        match self {
+            Self::None => name.to_string(),
+            Self::Nfc => name.nfc().collect(),
+            Self::Nfd => name.nfd().collect(),
+        }
+    }
+}
+
+/// Guess whether a raw name is CP949, UTF-8 or Shift_JIS.
+///
+/// Each candidate is scored by the malformed sequences in its decoding,
+/// then by how many decoded non-ASCII characters fall outside the scripts
+/// names are usually written in. Halfwidth kana (where CP949 text lands in
+/// Shift_JIS) and CP949's rare-syllable extension count against a guess.
+/// Ties go to CP949, so a name only decodes differently from ALZip's
+/// encoding when another one is clearly cleaner.
+pub fn detect(bytes: &[u8]) -> &'static Encoding {
+    [EUC_KR, UTF_8, SHIFT_JIS]
+        .into_iter()
+        .min_by_key(|encoding| score(encoding, bytes))
+        .unwrap_or(EUC_KR)
+}
+
+/// (replacement characters, implausible characters) for one decoding.
+fn score(encoding: &'static Encoding, bytes: &[u8]) -> (usize, usize) {
+    let (text, _) = encoding.decode_without_bom_handling(bytes);
+    let mut errors = 0;
+    let mut implausible = 0;
+    for c in text.chars().filter(|c| !c.is_ascii()) {
+        match c {
+            '\u{FFFD}' => errors += 1,
+            '\u{00C0}'..='\u{00FF}'     // Latin-1 letters
+            | '\u{2000}'..='\u{206F}'   // general punctuation
+            | '\u{3000}'..='\u{30FF}'   // CJK symbols, kana
+            | '\u{3130}'..='\u{318F}'   // Hangul compatibility jamo
+            | '\u{4E00}'..='\u{9FFF}'   // CJK ideographs
+            | '\u{AC00}'..='\u{D7A3}'   // Hangul syllables
+            | '\u{FF00}'..='\u{FF60}' => {} // fullwidth forms
+            _ => implausible += 1,
+        }
+    }
+    if encoding == EUC_KR {
+        implausible += uhc_extension_chars(bytes);
+    }
+    (errors, implausible)
+}
+
+/// Count CP949 characters from the UHC extension (lead or trail byte below
+/// 0xA1). These are rarely used Hangul syllables, and where Shift_JIS kana
+/// end up when read as CP949.
+fn uhc_extension_chars(bytes: &[u8]) -> usize {
+    let mut count = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        if (0x81..0xFF).contains(&bytes[i]) && i + 1 < bytes.len() {
+            if bytes[i] < 0xA1 || bytes[i + 1] < 0xA1 {
+                count += 1;
+            }
+            i += 2;
+        } else {
+            i += 1;
+        }
+    }
+    count
+}
+
 /// Convert CP949/EUC-KR encoded bytes to a UTF-8 string.
 /// ALZ archives store filenames in CP949 (a superset of EUC-KR).
 /// We use encoding_rs::EUC_KR which handles CP949 (MS949) as well.
@@ -28,6 +172,68 @@ mod tests {
         assert_eq!(cp949_to_utf8(cp949), "운영");
     }
 
+    #[test]
+    fn test_name_decoding_strategies() {
+        // Valid UTF-8 for "é", and the Hangul syllable "챕" in CP949.
+        let name = b"\xc3\xa9.txt";
+        assert_eq!(NameDecoding::Utf8First.decode(name), "é.txt");
+        assert_eq!(NameDecoding::Cp949.decode(name), "챕.txt");
+        assert_eq!(NameDecoding::Cp949.decode(b"\xbf\xee\xbf\xb5"), "운영");
+    }
+
+    #[test]
+    fn test_codepage_labels() {
+        assert_eq!(NameDecoding::from_label("CP949"), Some(NameDecoding::Cp949));
+        assert_eq!(
+            NameDecoding::from_label("euc-kr"),
+            Some(NameDecoding::Cp949)
+        );
+        let sjis = NameDecoding::from_label("shift_jis").unwrap();
+        assert_eq!(sjis, NameDecoding::Encoding(encoding_rs::SHIFT_JIS));
+        // "日本" in Shift_JIS.
+        assert_eq!(sjis.decode(b"\x93\xfa\x96\x7b"), "日本");
+        assert_eq!(
+            NameDecoding::from_label("gbk"),
+            Some(NameDecoding::Encoding(encoding_rs::GBK))
+        );
+        assert_eq!(
+            NameDecoding::from_label("utf-8")
+                .unwrap()
+                .decode("é".as_bytes()),
+            "é"
+        );
+        assert_eq!(NameDecoding::from_label("no-such-codepage"), None);
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(detect(b"plain.txt"), EUC_KR);
+        assert_eq!(detect(b"\xbf\xee\xbf\xb5.txt"), EUC_KR);
+        assert_eq!(
+            NameDecoding::Auto.decode(b"\xc7\xd1\xb1\xdb\xb9\xae\xbc\xad.hwp"),
+            "한글문서.hwp"
+        );
+        assert_eq!(
+            NameDecoding::Auto.decode(b"\xbb\xe7\xc1\xf8/\xbf\xa9\xb8\xa7\xc8\xde\xb0\xa1.jpg"),
+            "사진/여름휴가.jpg"
+        );
+        // A UHC-extension syllable alone does not flip the guess.
+        assert_eq!(
+            NameDecoding::Auto.decode(b"\x8cc\xb9\xe6\xb0\xa2\xc7\xcf.txt"),
+            "똠방각하.txt"
+        );
+        // Decodes cleanly both ways; CP949 wins the tie.
+        assert_eq!(detect(b"\xc3\xa9.txt"), EUC_KR);
+        // "テスト" in UTF-8 is not valid CP949.
+        assert_eq!(detect("テスト.txt".as_bytes()), UTF_8);
+        // "テスト" in Shift_JIS.
+        assert_eq!(detect(b"\x83\x65\x83\x58\x83\x67.txt"), SHIFT_JIS);
+        assert_eq!(
+            NameDecoding::Auto.decode(b"\x83\x65\x83\x58\x83\x67.txt"),
+            "テスト.txt"
+        );
+    }
+
     #[test]
     fn test_empty() {
         assert_eq!(cp949_to_utf8(b""), "");
@@ -1,8 +1,12 @@
 use std::fmt;
 
+use crate::format::ArchiveFormat;
+
 #[derive(Debug)]
 pub enum AlzError {
     NotAlzFile,
+    IsZipNotAlz,
+    UnsupportedFormat(ArchiveFormat),
     CorruptedFile,
     CantOpenFile(std::io::Error),
     CantOpenDestFile(std::io::Error),
@@ -10,11 +14,21 @@ pub enum AlzError {
     InflateFailed(String),
     Bzip2Failed(String),
     InvalidFileCrc { expected: u32, got: u32 },
+    SizeMismatch { expected: u64, got: u64 },
+    SizeLimitExceeded { name: String, limit: u64 },
+    SizeUnknown(String),
+    MissingVolume(String),
+    MissingEndRecord,
     InvalidSizeFieldWidth(u8),
     UnknownCompressionMethod(u8),
     PasswordNotSet,
     InvalidPassword,
     PathTraversal(String),
+    UnsafeFileName(String),
+    PathTooDeep(String),
+    NoSuchEntry(usize),
+    InvalidPattern(String),
+    Cancelled,
     Io(std::io::Error),
 }
 
@@ -22,6 +36,15 @@ impl fmt::Display for AlzError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::NotAlzFile => write!(f, "not an ALZ file"),
+            Self::IsZipNotAlz => {
+                write!(f, "not an ALZ file: this is a ZIP archive, use a ZIP tool")
+            }
+            Self::UnsupportedFormat(format) => {
+                write!(
+                    f,
+                    "not an ALZ file: this is an unsupported {format} archive"
+                )
+            }
             Self::CorruptedFile => write!(f, "corrupted file"),
             Self::CantOpenFile(e) => write!(f, "can't open archive file: {e}"),
             Self::CantOpenDestFile(e) => write!(f, "can't open dest file: {e}"),
@@ -34,6 +57,24 @@ impl fmt::Display for AlzError {
                     "invalid file CRC: expected {expected:08x}, got {got:08x}"
                 )
             }
+            Self::SizeMismatch { expected, got } => {
+                write!(f, "size mismatch: expected {expected} bytes, got {got}")
+            }
+            Self::SizeLimitExceeded { name, limit } => {
+                write!(f, "output exceeds the {limit}-byte limit: {name}")
+            }
+            Self::SizeUnknown(name) => {
+                write!(f, "entry has data but no size in its header: {name}")
+            }
+            Self::MissingVolume(name) => {
+                write!(
+                    f,
+                    "data runs past the last volume (truncated or missing volume): {name}"
+                )
+            }
+            Self::MissingEndRecord => {
+                write!(f, "archive ends without an end record (truncated?)")
+            }
             Self::InvalidSizeFieldWidth(v) => {
                 write!(f, "invalid size field width: 0x{v:02x}")
             }
@@ -41,6 +82,11 @@ impl fmt::Display for AlzError {
             Self::PasswordNotSet => write!(f, "password was not set"),
             Self::InvalidPassword => write!(f, "invalid password"),
             Self::PathTraversal(p) => write!(f, "path traversal blocked: {p}"),
+            Self::UnsafeFileName(p) => write!(f, "control character in file name: {p:?}"),
+            Self::PathTooDeep(p) => write!(f, "path nested too deeply: {p}"),
+            Self::NoSuchEntry(i) => write!(f, "no entry with index {i}"),
+            Self::InvalidPattern(s) => write!(f, "invalid glob pattern: {s}"),
+            Self::Cancelled => write!(f, "extraction cancelled"),
             Self::Io(e) => write!(f, "{e}"),
         }
     }
@@ -1,14 +1,296 @@
-use std::fs;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::io::{self, Read, Seek, SeekFrom, Write};
-use std::path::Path;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 
-use filetime::FileTime;
+#[cfg(feature = "sha256")]
+use sha2::{Digest, Sha256};
 
-use crate::archive::{AlzArchive, AlzFileEntry, CompressionMethod};
-use crate::crypto::ZipCrypto;
-use crate::decompress::{bzip2, deflate, raw};
-use crate::dostime::dos_datetime_to_systime;
+use crate::archive::{AlzArchive, AlzFileEntry, CompressionMethod, Parser};
+use crate::crypto::{HeaderCheck, ZipCrypto};
+use crate::decompress::{self, bzip2, deflate, raw};
+use crate::dostime::{DosTimeZone, dos_datetime_to_systime_in};
 use crate::error::{AlzError, AlzResult};
+use crate::multivolume::MultiVolumeReader;
+use crate::sink::{ExtractSink, MemorySink, PipeSink, RealFsSink};
+
+/// Progress callback: called after each chunk of an entry is written, with
+/// the entry, the bytes written so far and the CRC32 of those bytes.
+/// The difference between two calls for the same entry is the size of the
+/// chunk just written; with [`AlzArchive::total_uncompressed_size`] as the
+/// denominator that is enough to drive an overall progress bar.
+pub type ProgressFn<'a> = &'a (dyn Fn(&AlzFileEntry, u64, u32) + Sync);
+
+/// Entry callback: called once per entry with the result of extracting it,
+/// for callers that print their own per-entry messages.
+///
+/// Calls, like the progress messages, always come in archive order (or the
+/// order given to [`extract_entries_to`]), including for skipped and failed
+/// entries, so logs from two runs can be compared line by line. Any future
+/// concurrent extraction has to keep this order.
+pub type EntryFn<'a> = &'a (dyn Fn(&AlzFileEntry, &AlzResult<EntryOutcome>) + Sync);
+
+/// Content filter: called with the first [`PEEK_LEN`] decompressed bytes of
+/// a file (fewer if the file is shorter); the file is only written if it
+/// returns `true`.
+pub type ContentFilter<'a> = &'a (dyn Fn(&[u8]) -> bool + Sync);
+
+/// Number of leading bytes a [`ContentFilter`] gets to see.
+pub const PEEK_LEN: usize = 16;
+
+/// [`ContentFilter`] accepting common image formats (PNG, JPEG, GIF, BMP,
+/// WebP) by their signature.
+pub fn is_image(head: &[u8]) -> bool {
+    head.starts_with(b"\x89PNG\r\n\x1a\n")
+        || head.starts_with(&[0xFF, 0xD8, 0xFF])
+        || head.starts_with(b"GIF87a")
+        || head.starts_with(b"GIF89a")
+        || head.starts_with(b"BM")
+        || (head.starts_with(b"RIFF") && head.get(8..12) == Some(b"WEBP"))
+}
+
+/// Default for [`ExtractOptions::max_path_depth`].
+pub const DEFAULT_MAX_PATH_DEPTH: usize = 256;
+
+/// Settings for the `*_to` extraction functions.
+#[derive(Clone, Copy)]
+pub struct ExtractOptions<'a> {
+    /// Password for encrypted entries.
+    pub password: Option<&'a str>,
+    /// Suppress per-entry progress messages.
+    pub quiet: bool,
+    /// On a CRC mismatch, keep the output renamed to `<name>.corrupt`
+    /// instead of deleting it.
+    pub keep_corrupt: bool,
+    /// Keep files whose CRC does not match the header instead of failing
+    /// with [`AlzError::InvalidFileCrc`], for archivers known to write bad
+    /// CRCs. The size is still checked. A kept mismatch shows up as
+    /// [`EntryOutcome::crc`] differing from the entry's `file_crc`, and as a
+    /// warning in the per-entry messages.
+    pub ignore_crc: bool,
+    /// Continue with the remaining entries after one fails. The first
+    /// error is still returned once all entries have been tried. Entries
+    /// with an unknown compression method are skipped and not counted as
+    /// failures.
+    pub keep_going: bool,
+    /// Write the still-compressed (but decrypted) data of entries with an
+    /// unknown compression method to `<name>.raw` for external tools.
+    pub keep_unknown_raw: bool,
+    /// Size of the copy and decompression buffers, in bytes.
+    pub buf_size: usize,
+    /// Escape control characters in entry names as `%XX` instead of
+    /// rejecting the entry with [`AlzError::UnsafeFileName`].
+    pub sanitize: bool,
+    /// Time zone the archive's modification times were recorded in.
+    pub time_zone: DosTimeZone,
+    /// Fail when a modification time cannot be set instead of reporting
+    /// it in [`EntryOutcome::mtime_failed`] (or a warning, for directories).
+    pub strict_times: bool,
+    /// Relative directory prepended to every entry path inside the sink.
+    pub prefix: Option<&'a str>,
+    /// Reject entries whose names have more components than this with
+    /// [`AlzError::PathTooDeep`].
+    pub max_path_depth: usize,
+    /// When set, extraction stops between chunks, removes the partial file
+    /// and returns [`AlzError::Cancelled`].
+    pub cancel: Option<&'a AtomicBool>,
+    /// Called as file data is written; see [`ProgressFn`].
+    pub progress: Option<ProgressFn<'a>>,
+    /// Called after each entry, following its progress message; see
+    /// [`EntryFn`]. Independent of `quiet`.
+    pub on_entry: Option<EntryFn<'a>>,
+    /// Also hash each extracted file with SHA-256, reported in
+    /// [`EntryOutcome::sha256`]. The CRC32 check still runs.
+    #[cfg(feature = "sha256")]
+    pub sha256: bool,
+    /// Skip files that already exist in the sink with the entry's size and
+    /// modification time, e.g. to finish an interrupted extraction.
+    pub resume: bool,
+    /// With `resume`, also require the existing file's CRC to match.
+    pub resume_verify: bool,
+    /// Create each regular file at its uncompressed size without
+    /// decompressing it (see [`ExtractSink::create_placeholder`]). Nothing is
+    /// checked against the CRC and `content_filter` does not apply.
+    pub placeholders: bool,
+    /// Skip files (not directories) smaller than this many bytes.
+    pub min_size: Option<u64>,
+    /// Skip files larger than this many bytes.
+    pub max_size: Option<u64>,
+    /// Stop after extracting this many files; skipped entries and
+    /// directories don't count. See [`ExtractSummary::limited`].
+    pub limit: Option<usize>,
+    /// Of entries sharing a name, extract only the last one in the archive,
+    /// as ZIP tools do for archives that were appended to; see
+    /// [`latest_only`]. Not applied by [`extract_streaming`], which cannot
+    /// see later entries.
+    pub latest_only: bool,
+    /// Only write files whose leading bytes pass this filter; see
+    /// [`ContentFilter`]. Directories are skipped while a filter is set.
+    pub content_filter: Option<ContentFilter<'a>>,
+}
+
+impl std::fmt::Debug for ExtractOptions<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut ds = f.debug_struct("ExtractOptions");
+        ds.field("password", &self.password.map(|_| "***"))
+            .field("quiet", &self.quiet)
+            .field("keep_corrupt", &self.keep_corrupt)
+            .field("ignore_crc", &self.ignore_crc)
+            .field("keep_going", &self.keep_going)
+            .field("keep_unknown_raw", &self.keep_unknown_raw)
+            .field("buf_size", &self.buf_size)
+            .field("sanitize", &self.sanitize)
+            .field("time_zone", &self.time_zone)
+            .field("strict_times", &self.strict_times)
+            .field("prefix", &self.prefix)
+            .field("max_path_depth", &self.max_path_depth)
+            .field("cancel", &self.cancel)
+            .field("progress", &self.progress.is_some())
+            .field("on_entry", &self.on_entry.is_some());
+        #[cfg(feature = "sha256")]
+        ds.field("sha256", &self.sha256);
+        ds.field("placeholders", &self.placeholders)
+            .field("min_size", &self.min_size)
+            .field("max_size", &self.max_size)
+            .field("limit", &self.limit)
+            .field("latest_only", &self.latest_only)
+            .field("content_filter", &self.content_filter.is_some())
+            .finish()
+    }
+}
+
+impl ExtractOptions<'_> {
+    fn is_cancelled(&self) -> bool {
+        self.cancel.is_some_and(|c| c.load(Ordering::Relaxed))
+    }
+
+    /// Whether a file of `size` bytes passes `min_size` and `max_size`.
+    pub fn size_selected(&self, size: u64) -> bool {
+        self.min_size.is_none_or(|min| size >= min) && self.max_size.is_none_or(|max| size <= max)
+    }
+
+    fn limit_reached(&self, files: usize) -> bool {
+        self.limit.is_some_and(|n| files >= n)
+    }
+}
+
+/// Result of extracting one entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EntryOutcome {
+    /// CRC32 computed over the extracted data (0 for directories).
+    pub crc: u32,
+    /// Number of bytes written.
+    pub size: u64,
+    /// The entry was left out by [`ExtractOptions::content_filter`], the
+    /// size limits or [`ExtractOptions::resume`] and nothing was written.
+    pub skipped: bool,
+    /// SHA-256 of the extracted data, for files extracted with
+    /// `ExtractOptions::sha256` (requires the `sha256` feature).
+    pub sha256: Option<[u8; 32]>,
+    /// The file was written but its modification time could not be set,
+    /// e.g. on some network mounts. Only reported without
+    /// [`ExtractOptions::strict_times`], which turns this into an error.
+    pub mtime_failed: bool,
+}
+
+impl EntryOutcome {
+    const SKIPPED: EntryOutcome = EntryOutcome {
+        crc: 0,
+        size: 0,
+        skipped: true,
+        sha256: None,
+        mtime_failed: false,
+    };
+}
+
+/// Totals for a multi-entry extraction.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ExtractSummary {
+    /// Number of entries extracted successfully.
+    pub entries: usize,
+    /// Total bytes written across those entries.
+    pub bytes: u64,
+    /// Whether [`ExtractOptions::limit`] stopped extraction before the last
+    /// entry.
+    pub limited: bool,
+}
+
+impl ExtractSummary {
+    /// Count one extracted entry; skipped entries are ignored.
+    pub fn add(&mut self, outcome: EntryOutcome) {
+        if outcome.skipped {
+            return;
+        }
+        self.entries += 1;
+        self.bytes = self.bytes.saturating_add(outcome.size);
+    }
+}
+
+/// Everything known after a multi-entry extraction; see
+/// [`extract_all_report`].
+#[derive(Debug, Default)]
+pub struct ExtractReport {
+    /// Totals over the extracted entries.
+    pub summary: ExtractSummary,
+    /// The outcome for each entry that did not fail, by name, in the order
+    /// extracted. Skipped entries are included.
+    pub per_entry: Vec<(String, EntryOutcome)>,
+    /// Entries that failed, by name. Only ever non-empty with
+    /// [`ExtractOptions::keep_going`]; otherwise the first failure is
+    /// returned instead.
+    pub errors: Vec<(String, AlzError)>,
+}
+
+impl ExtractReport {
+    /// The summary, or the first error.
+    fn into_result(self) -> AlzResult<ExtractSummary> {
+        match self.errors.into_iter().next() {
+            Some((_, e)) => Err(e),
+            None => Ok(self.summary),
+        }
+    }
+
+    /// Report `dirs`, the result of setting directory mtimes, unless an
+    /// entry already failed.
+    fn finish(self, dirs: AlzResult<()>) -> AlzResult<Self> {
+        match dirs {
+            Err(e) if self.errors.is_empty() => Err(e),
+            _ => Ok(self),
+        }
+    }
+}
+
+impl Default for ExtractOptions<'_> {
+    fn default() -> Self {
+        ExtractOptions {
+            password: None,
+            quiet: false,
+            keep_corrupt: false,
+            ignore_crc: false,
+            keep_going: false,
+            keep_unknown_raw: false,
+            buf_size: decompress::DEFAULT_BUF_SIZE,
+            sanitize: false,
+            time_zone: DosTimeZone::Utc,
+            strict_times: false,
+            prefix: None,
+            max_path_depth: DEFAULT_MAX_PATH_DEPTH,
+            cancel: None,
+            progress: None,
+            on_entry: None,
+            #[cfg(feature = "sha256")]
+            sha256: false,
+            resume: false,
+            resume_verify: false,
+            placeholders: false,
+            min_size: None,
+            max_size: None,
+            limit: None,
+            latest_only: false,
+            content_filter: None,
+        }
+    }
+}
 
 /// Extract a single file entry from the archive.
 pub fn extract_entry(
@@ -18,148 +300,940 @@ pub fn extract_entry(
     password: Option<&str>,
     pipe_mode: bool,
 ) -> AlzResult<()> {
-    // Validate password for encrypted files.
-    let mut crypto = if entry.is_encrypted() {
-        let pwd = password.ok_or(AlzError::PasswordNotSet)?;
-        let enc_chk = entry.enc_check.as_ref().ok_or(AlzError::PasswordNotSet)?;
-        let mut c = ZipCrypto::new(pwd.as_bytes());
-        if !c.check_header(
-            enc_chk,
-            entry.file_crc,
-            entry.file_time_date,
-            entry.has_data_descriptor(),
-        ) {
-            return Err(AlzError::InvalidPassword);
-        }
-        // Re-initialize for actual decryption.
-        let mut c = ZipCrypto::new(pwd.as_bytes());
-        // Re-process the encryption header to advance key state.
-        let mut hdr_copy = *enc_chk;
-        c.decrypt(&mut hdr_copy);
-        Some(c)
-    } else {
-        None
+    extract_entry_from(&mut archive.reader, entry, dest_dir, password, pipe_mode)
+}
+
+/// Extract a single file entry, reading its data from `reader`.
+///
+/// Taking the reader rather than the whole archive lets callers iterate
+/// entries by reference while extracting; see
+/// [`AlzArchive::reader_and_entries`].
+pub fn extract_entry_from(
+    reader: &mut MultiVolumeReader,
+    entry: &AlzFileEntry,
+    dest_dir: &Path,
+    password: Option<&str>,
+    pipe_mode: bool,
+) -> AlzResult<()> {
+    let opts = ExtractOptions {
+        password,
+        ..Default::default()
     };
+    if pipe_mode {
+        extract_entry_to(reader, entry, &mut PipeSink, &opts)?;
+    } else {
+        extract_entry_to(reader, entry, &mut RealFsSink::new(dest_dir), &opts)?;
+    }
+    Ok(())
+}
 
-    // Build destination path.
-    let file_name = entry.file_name.replace('\\', "/");
+/// Extract a single file entry into `sink`.
+pub fn extract_entry_to(
+    reader: &mut MultiVolumeReader,
+    entry: &AlzFileEntry,
+    sink: &mut dyn ExtractSink,
+    opts: &ExtractOptions,
+) -> AlzResult<EntryOutcome> {
+    check_available(reader, entry)?;
 
-    // Security: reject path traversal.
-    if file_name.contains("../") || file_name.contains("..\\") {
-        return Err(AlzError::PathTraversal(file_name));
-    }
+    // Seek to data position.
+    reader.seek(SeekFrom::Start(entry.data_pos))?;
 
-    let dest_path = dest_dir.join(&file_name);
+    // Create a limited reader for exactly compressed_size bytes.
+    let mut limited = reader.take(entry.compressed_size);
+    extract_entry_data(&mut limited, entry, sink, opts)
+}
 
-    // Security: reject absolute paths and any remaining traversal.
-    if !pipe_mode {
-        let canonical_dest = fs::canonicalize(dest_dir)?;
-        // dest_path may not exist yet; resolve via its parent directory.
-        let resolved = if let Some(parent) = dest_path.parent() {
-            fs::create_dir_all(parent)?;
-            fs::canonicalize(parent)?.join(dest_path.file_name().unwrap_or_default())
-        } else {
-            dest_path.clone()
-        };
-        if !resolved.starts_with(&canonical_dest) {
-            return Err(AlzError::PathTraversal(file_name));
-        }
+/// Extract an entry whose data `limited` yields (at most `compressed_size`
+/// bytes, already positioned).
+fn extract_entry_data<R: Read>(
+    limited: &mut R,
+    entry: &AlzFileEntry,
+    sink: &mut dyn ExtractSink,
+    opts: &ExtractOptions,
+) -> AlzResult<EntryOutcome> {
+    #[cfg(feature = "tracing")]
+    let _span = tracing::debug_span!(
+        "entry",
+        name = %entry.file_name,
+        method = ?entry.compression_method,
+        size = entry.uncompressed_size,
+    )
+    .entered();
+
+    if opts.is_cancelled() {
+        return Err(AlzError::Cancelled);
+    }
+    if entry.size_unknown {
+        return Err(AlzError::SizeUnknown(entry.file_name.clone()));
     }
+    if !entry.is_directory() && !opts.size_selected(entry.uncompressed_size) {
+        return Ok(EntryOutcome::SKIPPED);
+    }
+
+    let path = entry_path(entry, opts)?;
+    let path = path.as_path();
 
     // Handle directories.
     if entry.is_directory() {
-        if !pipe_mode {
-            fs::create_dir_all(&dest_path)?;
+        if opts.content_filter.is_some() {
+            return Ok(EntryOutcome::SKIPPED);
         }
-        return Ok(());
+        sink.create_dir(path)?;
+        return Ok(EntryOutcome {
+            crc: 0,
+            size: 0,
+            skipped: false,
+            sha256: None,
+            mtime_failed: false,
+        });
+    }
+
+    // Placeholders need neither the data nor the password.
+    if opts.placeholders && !entry.is_symlink() {
+        sink.create_placeholder(path, entry.uncompressed_size)?;
+        let mtime_set = apply_mtime(entry, path, sink, opts)?;
+        return Ok(EntryOutcome {
+            crc: 0,
+            size: entry.uncompressed_size,
+            skipped: false,
+            sha256: None,
+            mtime_failed: !mtime_set,
+        });
+    }
+
+    if opts.resume && !entry.is_symlink() && already_extracted(entry, path, sink, opts) {
+        return Ok(EntryOutcome::SKIPPED);
     }
 
+    let mut crypto = entry_crypto(entry, opts.password)?;
+
     // Handle symlinks.
     if entry.is_symlink() {
-        archive.reader.seek(SeekFrom::Start(entry.data_pos))?;
-        let mut limited = (&mut archive.reader).take(entry.compressed_size);
-        let mut buf = Vec::new();
-        let crc = decompress_to(&mut limited, &mut buf, entry, crypto.as_mut())?;
-        if crc != entry.file_crc {
-            return Err(AlzError::InvalidFileCrc {
-                expected: entry.file_crc,
-                got: crc,
+        // The target is held in memory; stop at the declared size.
+        let mut buf = CappedWriter {
+            inner: Vec::new(),
+            remaining: entry.uncompressed_size,
+            exceeded: false,
+        };
+        let result = decompress_to(limited, &mut buf, entry, crypto.as_mut(), opts.buf_size);
+        if buf.exceeded {
+            return Err(AlzError::SizeLimitExceeded {
+                name: entry.file_name.clone(),
+                limit: entry.uncompressed_size,
             });
         }
+        let crc = result?;
+        let buf = buf.inner;
+        verify(entry, crc, buf.len() as u64, opts.ignore_crc)?;
+        if opts.content_filter.is_some_and(|filter| !filter(&buf)) {
+            return Ok(EntryOutcome::SKIPPED);
+        }
         let target = String::from_utf8_lossy(&buf);
-        if pipe_mode {
-            let stdout = io::stdout();
-            let mut out = stdout.lock();
-            out.write_all(target.as_bytes())
-                .map_err(AlzError::CantOpenDestFile)?;
-        } else {
-            let target_path = Path::new(target.as_ref());
-            if target.contains("../") || target.contains("..\\") || target_path.has_root() {
-                return Err(AlzError::PathTraversal(target.into_owned()));
-            }
-            #[cfg(unix)]
-            std::os::unix::fs::symlink(target.as_ref(), &dest_path)?;
-            #[cfg(not(unix))]
-            fs::write(&dest_path, target.as_bytes())?;
+        sink.symlink(&target, path)?;
+        return Ok(EntryOutcome {
+            crc,
+            size: buf.len() as u64,
+            skipped: false,
+            sha256: None,
+            mtime_failed: false,
+        });
+    }
+
+    if let CompressionMethod::Unknown(n) = entry.compression_method {
+        if !opts.keep_unknown_raw {
+            // Fail before creating an output file.
+            return Err(AlzError::UnknownCompressionMethod(n));
         }
-        return Ok(());
+        let mut raw_path = path.as_os_str().to_owned();
+        raw_path.push(".raw");
+        let mut out = sink.create_file(Path::new(&raw_path))?;
+        let crc = raw::extract_raw_buffered(
+            limited,
+            &mut out,
+            entry.compressed_size,
+            crypto.as_mut(),
+            opts.buf_size,
+        )?;
+        out.flush().map_err(AlzError::CantOpenDestFile)?;
+        return Ok(EntryOutcome {
+            crc,
+            size: entry.compressed_size,
+            skipped: false,
+            sha256: None,
+            mtime_failed: false,
+        });
     }
 
-    // Seek to data position.
-    archive.reader.seek(SeekFrom::Start(entry.data_pos))?;
+    // Decompress and write.
+    let mut out = TrackingWriter {
+        inner: PeekWriter::new(sink, path, opts.content_filter, opts.buf_size)?,
+        entry,
+        written: 0,
+        running: opts.progress.map(|p| (p, crc32fast::Hasher::new())),
+        cancel: opts.cancel,
+        #[cfg(feature = "sha256")]
+        sha256: opts.sha256.then(Sha256::new),
+    };
+    let result =
+        decompress_to(limited, &mut out, entry, crypto.as_mut(), opts.buf_size).and_then(|crc| {
+            out.inner.decide()?;
+            out.flush().map_err(AlzError::CantOpenDestFile)?;
+            Ok(crc)
+        });
+    let size = out.written;
+    #[cfg(feature = "sha256")]
+    let sha256 = out.sha256.take().map(|hasher| hasher.finalize().into());
+    #[cfg(not(feature = "sha256"))]
+    let sha256 = None;
+    let rejected = out.inner.finish();
+    drop(out);
+    if opts.is_cancelled() {
+        let _ = sink.remove_file(path);
+        return Err(AlzError::Cancelled);
+    }
+    if rejected? {
+        return Ok(EntryOutcome::SKIPPED);
+    }
+    let crc = result?;
 
-    // Create a limited reader for exactly compressed_size bytes.
-    let mut limited = (&mut archive.reader).take(entry.compressed_size);
+    // Set file modification time; a strict failure is only returned once
+    // the data has been verified.
+    let mtime_set = apply_mtime(entry, path, sink, opts);
 
-    // Decompress and write.
-    let crc = if pipe_mode {
-        let stdout = io::stdout();
-        let mut out = stdout.lock();
-        decompress_to(&mut limited, &mut out, entry, crypto.as_mut())?
-    } else {
-        let mut file = fs::File::create(&dest_path).map_err(AlzError::CantOpenDestFile)?;
-        let crc = decompress_to(&mut limited, &mut file, entry, crypto.as_mut())?;
-        file.flush().map_err(AlzError::CantOpenDestFile)?;
-        drop(file);
+    // Verify size and CRC.
+    if let Err(e) = verify(entry, crc, size, opts.ignore_crc) {
+        if opts.keep_corrupt {
+            let mut corrupt = path.as_os_str().to_owned();
+            corrupt.push(".corrupt");
+            let _ = sink.rename(path, Path::new(&corrupt));
+        } else {
+            let _ = sink.remove_file(path);
+        }
+        return Err(e);
+    }
 
-        // Set file modification time.
-        if let Some(systime) = dos_datetime_to_systime(entry.file_time_date) {
-            let ft = FileTime::from_system_time(systime);
-            let _ = filetime::set_file_mtime(&dest_path, ft);
+    Ok(EntryOutcome {
+        crc,
+        size,
+        skipped: false,
+        sha256,
+        mtime_failed: !mtime_set?,
+    })
+}
+
+/// Set `path`'s modification time from `entry`. Returns whether it was set;
+/// with `strict_times` a failure is an error instead.
+fn apply_mtime(
+    entry: &AlzFileEntry,
+    path: &Path,
+    sink: &mut dyn ExtractSink,
+    opts: &ExtractOptions,
+) -> AlzResult<bool> {
+    let Some(systime) = dos_datetime_to_systime_in(entry.file_time_date, opts.time_zone) else {
+        return Ok(true);
+    };
+    match sink.set_mtime(path, systime) {
+        Ok(()) => Ok(true),
+        Err(e) if opts.strict_times => Err(e),
+        Err(_e) => {
+            #[cfg(feature = "tracing")]
+            tracing::warn!(error = %_e, "could not set modification time");
+            Ok(false)
         }
+    }
+}
 
-        crc
+/// Whether the sink already holds `entry` at `path`: same size, same
+/// mtime (where known) and, with `resume_verify`, same CRC.
+fn already_extracted(
+    entry: &AlzFileEntry,
+    path: &Path,
+    sink: &mut dyn ExtractSink,
+    opts: &ExtractOptions,
+) -> bool {
+    let Some((size, mtime)) = sink.existing(path) else {
+        return false;
     };
+    if size != entry.uncompressed_size {
+        return false;
+    }
+    if let (Some(mtime), Some(expected)) = (
+        mtime,
+        dos_datetime_to_systime_in(entry.file_time_date, opts.time_zone),
+    ) && mtime != expected
+    {
+        return false;
+    }
+    if !opts.resume_verify {
+        return true;
+    }
+    sink.open_existing(path)
+        .is_some_and(|file| entry.verify_data(file).unwrap_or(false))
+}
+
+/// Path of `entry` relative to the sink, after the name checks and
+/// [`ExtractOptions::prefix`].
+fn entry_path(entry: &AlzFileEntry, opts: &ExtractOptions) -> AlzResult<PathBuf> {
+    let file_name = check_control_chars(entry.normalized_name(), opts.sanitize)?;
+
+    // Security: reject path traversal. The sink rejects absolute paths and
+    // anything else that escapes its destination.
+    if file_name.contains("../") || file_name.contains("..\\") {
+        return Err(AlzError::PathTraversal(file_name));
+    }
+    if entry.components().count() > opts.max_path_depth {
+        return Err(AlzError::PathTooDeep(file_name));
+    }
+    match opts.prefix {
+        Some(prefix) => {
+            let prefix = Path::new(prefix);
+            if !prefix
+                .components()
+                .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+            {
+                return Err(AlzError::PathTraversal(prefix.display().to_string()));
+            }
+            Ok(prefix.join(&file_name))
+        }
+        None => Ok(PathBuf::from(file_name)),
+    }
+}
 
-    // Verify CRC.
-    if crc != entry.file_crc {
-        if !pipe_mode {
-            let _ = fs::remove_file(&dest_path);
+/// Give extracted directories their archived mtimes. This runs after all
+/// files are written, since adding a file to a directory updates its mtime.
+///
+/// A failure is printed as a warning unless `quiet`, or returned with
+/// `strict_times`.
+fn set_dir_mtimes<'e>(
+    dirs: impl IntoIterator<Item = &'e AlzFileEntry>,
+    sink: &mut dyn ExtractSink,
+    opts: &ExtractOptions,
+) -> AlzResult<()> {
+    if opts.content_filter.is_some() {
+        return Ok(());
+    }
+    for entry in dirs {
+        if let Ok(path) = entry_path(entry, opts)
+            && !apply_mtime(entry, &path, sink, opts)?
+            && !opts.quiet
+        {
+            eprint!(
+                "\nwarning: could not set modification time of {}",
+                entry.file_name
+            );
         }
+    }
+    Ok(())
+}
+
+/// Check the produced byte count and CRC against the header. The size is
+/// checked first: a truncated stream could collide on CRC alone. Entries
+/// without a CRC (see [`AlzFileEntry::has_crc`]) only have their size
+/// checked.
+fn verify(entry: &AlzFileEntry, crc: u32, size: u64, ignore_crc: bool) -> AlzResult<()> {
+    if size != entry.uncompressed_size {
+        return Err(AlzError::SizeMismatch {
+            expected: entry.uncompressed_size,
+            got: size,
+        });
+    }
+    if crc != entry.file_crc && entry.has_crc() && !ignore_crc {
         return Err(AlzError::InvalidFileCrc {
             expected: entry.file_crc,
             got: crc,
         });
     }
+    Ok(())
+}
 
+/// Reject names containing NUL or other control characters, which some
+/// filesystems refuse and C APIs truncate at; with `sanitize`, escape them.
+fn check_control_chars(name: String, sanitize: bool) -> AlzResult<String> {
+    if !name.chars().any(|c| c < ' ') {
+        return Ok(name);
+    }
+    if !sanitize {
+        return Err(AlzError::UnsafeFileName(name));
+    }
+    Ok(name
+        .chars()
+        .map(|c| {
+            if c < ' ' {
+                format!("%{:02X}", c as u32)
+            } else {
+                c.to_string()
+            }
+        })
+        .collect())
+}
+
+/// Counts bytes written and, with a progress callback, reports a running CRC.
+/// Fails writes once `cancel` is set, which stops the decompressor.
+struct TrackingWriter<'a, W> {
+    inner: W,
+    entry: &'a AlzFileEntry,
+    written: u64,
+    running: Option<(ProgressFn<'a>, crc32fast::Hasher)>,
+    cancel: Option<&'a AtomicBool>,
+    #[cfg(feature = "sha256")]
+    sha256: Option<Sha256>,
+}
+
+impl<W: Write> Write for TrackingWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.cancel.is_some_and(|c| c.load(Ordering::Relaxed)) {
+            return Err(io::Error::other("cancelled"));
+        }
+        let n = self.inner.write(buf)?;
+        self.written += n as u64;
+        if let Some((progress, hasher)) = &mut self.running {
+            hasher.update(&buf[..n]);
+            progress(self.entry, self.written, hasher.clone().finalize());
+        }
+        #[cfg(feature = "sha256")]
+        if let Some(hasher) = &mut self.sha256 {
+            hasher.update(&buf[..n]);
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Batch small decoder writes into fewer writes to the sink. Callers flush
+/// before setting the mtime or reporting success.
+fn buffered<'s>(out: Box<dyn Write + 's>, buf_size: usize) -> Box<dyn Write + 's> {
+    Box::new(io::BufWriter::with_capacity(buf_size, out))
+}
+
+/// Holds back the first [`PEEK_LEN`] bytes of a file until the content
+/// filter has accepted them, and only then creates the file in the sink.
+/// Once rejected, writes fail so the decompressor stops early. Without a
+/// filter the file is created up front.
+struct PeekWriter<'s> {
+    state: PeekState<'s>,
+    path: &'s Path,
+    filter: Option<ContentFilter<'s>>,
+    /// Capacity of the `BufWriter` around the created file.
+    buf_size: usize,
+    /// Error from creating the file after the filter passed.
+    err: Option<AlzError>,
+}
+
+enum PeekState<'s> {
+    Pending {
+        sink: &'s mut dyn ExtractSink,
+        head: Vec<u8>,
+    },
+    Open(Box<dyn Write + 's>),
+    Rejected,
+}
+
+impl<'s> PeekWriter<'s> {
+    fn new(
+        sink: &'s mut dyn ExtractSink,
+        path: &'s Path,
+        filter: Option<ContentFilter<'s>>,
+        buf_size: usize,
+    ) -> AlzResult<Self> {
+        let state = match filter {
+            Some(_) => PeekState::Pending {
+                sink,
+                head: Vec::with_capacity(PEEK_LEN),
+            },
+            None => PeekState::Open(buffered(sink.create_file(path)?, buf_size)),
+        };
+        Ok(PeekWriter {
+            state,
+            path,
+            filter,
+            buf_size,
+            err: None,
+        })
+    }
+
+    /// Run the filter over the held-back bytes, if not done yet, and create
+    /// the file when they pass.
+    fn decide(&mut self) -> AlzResult<()> {
+        let state = std::mem::replace(&mut self.state, PeekState::Rejected);
+        let PeekState::Pending { sink, head } = state else {
+            self.state = state;
+            return Ok(());
+        };
+        if !self.filter.is_some_and(|filter| filter(&head)) {
+            return Ok(());
+        }
+        let mut out = buffered(sink.create_file(self.path)?, self.buf_size);
+        out.write_all(&head).map_err(AlzError::CantOpenDestFile)?;
+        self.state = PeekState::Open(out);
+        Ok(())
+    }
+
+    /// Report whether the filter rejected the file.
+    fn finish(&mut self) -> AlzResult<bool> {
+        match self.err.take() {
+            Some(e) => Err(e),
+            None => Ok(matches!(self.state, PeekState::Rejected)),
+        }
+    }
+}
+
+impl Write for PeekWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let PeekState::Pending { head, .. } = &mut self.state else {
+            return match &mut self.state {
+                PeekState::Open(out) => out.write(buf),
+                _ => Err(io::Error::other("filtered out")),
+            };
+        };
+        let n = (PEEK_LEN - head.len()).min(buf.len());
+        head.extend_from_slice(&buf[..n]);
+        if head.len() == PEEK_LEN
+            && let Err(e) = self.decide()
+        {
+            let msg = e.to_string();
+            self.err = Some(e);
+            return Err(io::Error::other(msg));
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.state {
+            PeekState::Open(out) => out.flush(),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Open a reader over an entry's decompressed contents.
+///
+/// The reader borrows `reader` and does not verify the CRC; see
+/// [`decompress::reader_for`].
+pub fn entry_reader<'a>(
+    reader: &'a mut MultiVolumeReader,
+    entry: &AlzFileEntry,
+    password: Option<&str>,
+) -> AlzResult<Box<dyn Read + 'a>> {
+    let crypto = entry_crypto(entry, password)?;
+    check_available(reader, entry)?;
+    reader.seek(SeekFrom::Start(entry.data_pos))?;
+    decompress::reader_for(
+        entry.compression_method,
+        reader,
+        entry.compressed_size,
+        crypto,
+    )
+}
+
+/// Fail with [`AlzError::MissingVolume`] if the volumes end before the
+/// entry's data does, rather than letting a decoder hit a short read.
+fn check_available(reader: &MultiVolumeReader, entry: &AlzFileEntry) -> AlzResult<()> {
+    if entry.data_pos.saturating_add(entry.compressed_size) > reader.total_size() {
+        return Err(AlzError::MissingVolume(entry.file_name.clone()));
+    }
     Ok(())
 }
 
+/// Set up decryption for an entry, validating the password.
+/// Returns `None` for unencrypted entries.
+fn entry_crypto(entry: &AlzFileEntry, password: Option<&str>) -> AlzResult<Option<ZipCrypto>> {
+    if !entry.is_encrypted() {
+        return Ok(None);
+    }
+    let pwd = password.ok_or(AlzError::PasswordNotSet)?;
+    let enc_chk = entry.enc_check.as_ref().ok_or(AlzError::PasswordNotSet)?;
+    let mut c = ZipCrypto::new(pwd.as_bytes());
+    let check = c.check_header_detailed(
+        enc_chk,
+        entry.file_crc,
+        entry.file_time_date,
+        entry.has_data_descriptor(),
+    );
+    if check != HeaderCheck::Ok {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(?check, "encryption header check failed");
+        return Err(AlzError::InvalidPassword);
+    }
+    // Re-initialize for actual decryption.
+    let mut c = ZipCrypto::new(pwd.as_bytes());
+    // Re-process the encryption header to advance key state.
+    let mut hdr_copy = *enc_chk;
+    c.decrypt(&mut hdr_copy);
+    Ok(Some(c))
+}
+
 fn decompress_to<R: io::Read, W: Write>(
     reader: &mut R,
     writer: &mut W,
     entry: &AlzFileEntry,
     crypto: Option<&mut ZipCrypto>,
+    buf_size: usize,
 ) -> AlzResult<u32> {
-    match entry.compression_method {
-        CompressionMethod::Store => raw::extract_raw(reader, writer, entry.compressed_size, crypto),
+    let size = entry.compressed_size;
+    // A decoder given no input produces nothing, which would otherwise
+    // surface as a CRC mismatch against the empty output.
+    if size == 0
+        && entry.uncompressed_size > 0
+        && matches!(
+            entry.compression_method,
+            CompressionMethod::Deflate | CompressionMethod::Bzip2
+        )
+    {
+        return Err(AlzError::SizeMismatch {
+            expected: entry.uncompressed_size,
+            got: 0,
+        });
+    }
+    #[cfg(feature = "tracing")]
+    tracing::debug!(compressed_size = size, "decompression start");
+    let result = match entry.compression_method {
+        CompressionMethod::Store => {
+            raw::extract_raw_buffered(reader, writer, size, crypto, buf_size)
+        }
         CompressionMethod::Deflate => {
-            deflate::extract_deflate(reader, writer, entry.compressed_size, crypto)
+            deflate::extract_deflate_buffered(reader, writer, size, crypto, buf_size)
         }
         CompressionMethod::Bzip2 => {
-            bzip2::extract_bzip2(reader, writer, entry.compressed_size, crypto)
+            bzip2::extract_bzip2_buffered(reader, writer, size, crypto, buf_size)
         }
         CompressionMethod::Unknown(n) => Err(AlzError::UnknownCompressionMethod(n)),
+    };
+    #[cfg(feature = "tracing")]
+    match &result {
+        Ok(crc) => tracing::debug!(crc, "decompression end"),
+        Err(e) => tracing::debug!(error = %e, "decompression failed"),
+    }
+    result
+}
+
+/// Read `len` bytes of an entry's uncompressed data starting at `offset`.
+///
+/// The result is truncated at the end of the entry. For unencrypted Store
+/// entries this seeks straight to the requested bytes, so the cost is
+/// proportional to `len`. Encrypted Store entries must run the cipher over
+/// the first `offset` bytes to reach the right key state, and compressed
+/// entries are decompressed (and discarded) up to `offset`, so both cost
+/// time proportional to `offset + len`.
+pub fn extract_range(
+    archive: &mut AlzArchive,
+    entry: &AlzFileEntry,
+    password: Option<&str>,
+    offset: u64,
+    len: u64,
+) -> AlzResult<Vec<u8>> {
+    if entry.is_directory() || offset >= entry.uncompressed_size {
+        return Ok(Vec::new());
+    }
+    let len = len.min(entry.uncompressed_size - offset);
+    let mut crypto = entry_crypto(entry, password)?;
+    let reader = &mut archive.reader;
+
+    if entry.compression_method == CompressionMethod::Store {
+        let len = len.min(entry.compressed_size.saturating_sub(offset));
+        let mut out = vec![0u8; len.try_into().map_err(|_| AlzError::CorruptedFile)?];
+        match crypto {
+            None => {
+                let start = entry
+                    .data_pos
+                    .checked_add(offset)
+                    .ok_or(AlzError::CorruptedFile)?;
+                reader.seek(SeekFrom::Start(start))?;
+                reader.read_exact(&mut out)?;
+            }
+            Some(ref mut c) => {
+                reader.seek(SeekFrom::Start(entry.data_pos))?;
+                let mut limited = reader.take(offset);
+                raw::extract_raw(&mut limited, &mut io::sink(), offset, Some(c))?;
+                reader.read_exact(&mut out)?;
+                c.decrypt(&mut out);
+            }
+        }
+        return Ok(out);
+    }
+
+    reader.seek(SeekFrom::Start(entry.data_pos))?;
+    let mut limited = reader.take(entry.compressed_size);
+    let mut window = RangeWriter {
+        skip: offset,
+        remaining: len,
+        out: Vec::new(),
+    };
+    match decompress_to(
+        &mut limited,
+        &mut window,
+        entry,
+        crypto.as_mut(),
+        decompress::DEFAULT_BUF_SIZE,
+    ) {
+        Ok(_) => {}
+        // The window fills before the stream ends; the rest is not needed.
+        Err(AlzError::CantOpenDestFile(_)) if window.remaining == 0 => {}
+        Err(e) => return Err(e),
+    }
+    Ok(window.out)
+}
+
+/// Writer that fails, setting `exceeded`, once more than `remaining` bytes
+/// arrive, stopping decompression early.
+struct CappedWriter<W> {
+    inner: W,
+    remaining: u64,
+    exceeded: bool,
+}
+
+impl<W: Write> Write for CappedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.len() as u64 > self.remaining {
+            self.exceeded = true;
+            return Err(io::Error::other("size limit exceeded"));
+        }
+        let n = self.inner.write(buf)?;
+        self.remaining -= n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Writer that keeps only the bytes in `[skip, skip + remaining)` and fails
+/// once the window is full, stopping decompression early.
+struct RangeWriter {
+    skip: u64,
+    remaining: u64,
+    out: Vec<u8>,
+}
+
+impl Write for RangeWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.remaining == 0 {
+            return Err(io::Error::other("range complete"));
+        }
+        let skipped = (self.skip.min(buf.len() as u64)) as usize;
+        self.skip -= skipped as u64;
+        let rest = &buf[skipped..];
+        let take = (self.remaining.min(rest.len() as u64)) as usize;
+        self.out.extend_from_slice(&rest[..take]);
+        self.remaining -= take as u64;
+        Ok(skipped + take)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Per-entry results of [`test_archive`], in archive order.
+pub type TestResults = Vec<(String, AlzResult<()>)>;
+
+/// Decompress every file entry without writing it anywhere and check its
+/// size and CRC, like `unzip -t`. Directories are left out; a failing entry
+/// does not stop the others.
+pub fn test_archive(archive: &mut AlzArchive, password: Option<&str>) -> TestResults {
+    let (reader, entries) = archive.reader_and_entries();
+    entries
+        .iter()
+        .filter(|e| !e.is_directory())
+        .map(|e| (e.file_name.clone(), test_entry(reader, e, password)))
+        .collect()
+}
+
+/// [`test_archive`] with the entries spread over rayon's thread pool. Each
+/// worker reads through its own reopened copy of the volumes, so this needs
+/// an archive [`AlzArchive::try_clone`] can handle. The results are the
+/// same as the sequential version's, in the same order.
+#[cfg(feature = "rayon")]
+pub fn test_archive_parallel(
+    archive: &AlzArchive,
+    password: Option<&str>,
+) -> io::Result<TestResults> {
+    use rayon::prelude::*;
+
+    let reopener = archive.reader.reopener();
+    // Fail up front rather than once per entry.
+    drop(reopener.open()?);
+    Ok(archive
+        .entries
+        .par_iter()
+        .filter(|e| !e.is_directory())
+        .map_init(
+            || reopener.open(),
+            |reader, e| {
+                let result = match reader {
+                    Ok(reader) => test_entry(reader, e, password),
+                    Err(err) => Err(AlzError::Io(io::Error::new(err.kind(), err.to_string()))),
+                };
+                (e.file_name.clone(), result)
+            },
+        )
+        .collect())
+}
+
+fn test_entry(
+    reader: &mut MultiVolumeReader,
+    entry: &AlzFileEntry,
+    password: Option<&str>,
+) -> AlzResult<()> {
+    if entry.size_unknown {
+        return Err(AlzError::SizeUnknown(entry.file_name.clone()));
+    }
+    let mut crypto = entry_crypto(entry, password)?;
+    check_available(reader, entry)?;
+    reader.seek(SeekFrom::Start(entry.data_pos))?;
+    let mut limited = reader.take(entry.compressed_size);
+    let mut counter = TrackingWriter {
+        inner: io::sink(),
+        entry,
+        written: 0,
+        running: None,
+        cancel: None,
+        #[cfg(feature = "sha256")]
+        sha256: None,
+    };
+    let crc = decompress_to(
+        &mut limited,
+        &mut counter,
+        entry,
+        crypto.as_mut(),
+        decompress::DEFAULT_BUF_SIZE,
+    )?;
+    verify(entry, crc, counter.written, false)
+}
+
+/// A difference between an archive and a directory it was extracted into;
+/// see [`verify_against_dir`]. Paths are relative to the directory.
+#[derive(Debug)]
+pub enum Discrepancy {
+    /// An entry has nothing at its path.
+    Missing(PathBuf),
+    /// A file's size differs from the entry's.
+    SizeMismatch {
+        path: PathBuf,
+        expected: u64,
+        got: u64,
+    },
+    /// A file has the right size but different contents.
+    CrcMismatch(PathBuf),
+    /// Something is at an entry's path but is not the right kind of thing,
+    /// e.g. a directory where a file belongs.
+    WrongType(PathBuf),
+    /// A file or directory that no entry accounts for.
+    Unexpected(PathBuf),
+    /// A path could not be read.
+    Unreadable(PathBuf, io::Error),
+}
+
+/// Compare `dir` against a previous extraction of `archive`: every entry
+/// must be present with the archived size and CRC, and nothing else may be
+/// there. Entries are checked at their plain names (no
+/// [`ExtractOptions::prefix`]); for duplicate names the last entry counts.
+/// Only `dir` is read, not the archive's data.
+pub fn verify_against_dir(archive: &AlzArchive, dir: &Path) -> Vec<Discrepancy> {
+    let opts = ExtractOptions::default();
+    let mut expected = BTreeMap::new();
+    for entry in &archive.entries {
+        // Entries extraction would refuse are not expected on disk.
+        if let Ok(path) = entry_path(entry, &opts) {
+            expected.insert(path.components().collect::<PathBuf>(), entry);
+        }
+    }
+
+    let mut found = Vec::new();
+    for (path, entry) in &expected {
+        if let Some(d) = check_on_disk(entry, path, &dir.join(path)) {
+            found.push(d);
+        }
+    }
+
+    // Directories that hold expected entries without being entries themselves.
+    let parents: HashSet<&Path> = expected
+        .keys()
+        .flat_map(|p| p.ancestors().skip(1))
+        .collect();
+    walk_unexpected(dir, Path::new(""), &expected, &parents, &mut found);
+    found
+}
+
+fn check_on_disk(entry: &AlzFileEntry, rel: &Path, path: &Path) -> Option<Discrepancy> {
+    let meta = match std::fs::symlink_metadata(path) {
+        Ok(meta) => meta,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            return Some(Discrepancy::Missing(rel.to_path_buf()));
+        }
+        Err(e) => return Some(Discrepancy::Unreadable(rel.to_path_buf(), e)),
+    };
+    if entry.is_directory() {
+        return (!meta.is_dir()).then(|| Discrepancy::WrongType(rel.to_path_buf()));
+    }
+    // Symlinks are extracted as real links on Unix only.
+    #[cfg(unix)]
+    if entry.is_symlink() {
+        if !meta.is_symlink() {
+            return Some(Discrepancy::WrongType(rel.to_path_buf()));
+        }
+        return match std::fs::read_link(path) {
+            Ok(target) => {
+                let target = target.to_string_lossy();
+                match entry.verify_data(target.as_bytes()) {
+                    Ok(true) => None,
+                    _ => Some(Discrepancy::CrcMismatch(rel.to_path_buf())),
+                }
+            }
+            Err(e) => Some(Discrepancy::Unreadable(rel.to_path_buf(), e)),
+        };
+    }
+    if !meta.is_file() {
+        return Some(Discrepancy::WrongType(rel.to_path_buf()));
+    }
+    if meta.len() != entry.uncompressed_size {
+        return Some(Discrepancy::SizeMismatch {
+            path: rel.to_path_buf(),
+            expected: entry.uncompressed_size,
+            got: meta.len(),
+        });
+    }
+    match entry.verify_file(path) {
+        Ok(true) => None,
+        Ok(false) => Some(Discrepancy::CrcMismatch(rel.to_path_buf())),
+        Err(e) => Some(Discrepancy::Unreadable(rel.to_path_buf(), e)),
+    }
+}
+
+/// Report everything under `dir.join(rel)` that is neither expected nor a
+/// parent of something expected. Unexpected directories are reported once,
+/// without their contents.
+fn walk_unexpected(
+    dir: &Path,
+    rel: &Path,
+    expected: &BTreeMap<PathBuf, &AlzFileEntry>,
+    parents: &HashSet<&Path>,
+    found: &mut Vec<Discrepancy>,
+) {
+    let read = match std::fs::read_dir(dir.join(rel)) {
+        Ok(read) => read,
+        // A missing destination already shows up as missing entries.
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return,
+        Err(e) => {
+            found.push(Discrepancy::Unreadable(rel.to_path_buf(), e));
+            return;
+        }
+    };
+    let mut children: Vec<_> = read.filter_map(|e| e.ok()).collect();
+    children.sort_by_key(|e| e.file_name());
+    for child in children {
+        let child_rel = rel.join(child.file_name());
+        let is_dir = child.file_type().is_ok_and(|t| t.is_dir());
+        let is_entry = expected.contains_key(&child_rel);
+        if is_dir && (parents.contains(child_rel.as_path()) || is_entry) {
+            walk_unexpected(dir, &child_rel, expected, parents, found);
+        } else if !is_entry {
+            found.push(Discrepancy::Unexpected(child_rel));
+        }
     }
 }
 
@@ -171,18 +1245,369 @@ pub fn extract_all(
     pipe_mode: bool,
     quiet: bool,
 ) -> AlzResult<()> {
-    let entries: Vec<AlzFileEntry> = archive.entries.clone();
-    for entry in &entries {
-        if !quiet && !pipe_mode {
-            eprint!(
-                "\nextracting : {} ({}bytes) ",
-                entry.file_name, entry.uncompressed_size
-            );
+    let opts = ExtractOptions {
+        password,
+        quiet: quiet || pipe_mode,
+        ..Default::default()
+    };
+    if pipe_mode {
+        extract_all_to(archive, &mut PipeSink, &opts)?;
+    } else {
+        extract_all_to(archive, &mut RealFsSink::new(dest_dir), &opts)?;
+    }
+    Ok(())
+}
+
+/// Result of one archive in [`extract_glob`].
+#[derive(Debug)]
+pub struct ArchiveResult {
+    pub path: PathBuf,
+    pub result: AlzResult<ExtractSummary>,
+}
+
+/// Extract every archive matching the glob `pattern` into
+/// `dest_dir/<archive stem>/`.
+///
+/// Archives are handled independently: one that fails to open or extract
+/// is recorded in its [`ArchiveResult`] and the batch continues. Only a
+/// malformed pattern fails the whole call.
+pub fn extract_glob(
+    pattern: &str,
+    dest_dir: &Path,
+    opts: &ExtractOptions,
+) -> AlzResult<Vec<ArchiveResult>> {
+    let paths = glob::glob(pattern).map_err(|e| AlzError::InvalidPattern(e.to_string()))?;
+    let mut results = Vec::new();
+    for path in paths {
+        let (path, result) = match path {
+            Ok(path) => {
+                let result = extract_archive_into(&path, dest_dir, opts);
+                (path, result)
+            }
+            Err(e) => (
+                e.path().to_path_buf(),
+                Err(AlzError::CantOpenFile(e.into())),
+            ),
+        };
+        results.push(ArchiveResult { path, result });
+    }
+    Ok(results)
+}
+
+fn extract_archive_into(
+    path: &Path,
+    dest_dir: &Path,
+    opts: &ExtractOptions,
+) -> AlzResult<ExtractSummary> {
+    let stem = path.file_stem().unwrap_or(path.as_os_str());
+    let mut archive = AlzArchive::from_reader(MultiVolumeReader::open(path)?)?;
+    extract_all_to(
+        &mut archive,
+        &mut RealFsSink::new(dest_dir.join(stem)),
+        opts,
+    )
+}
+
+/// Extract all entries from the archive into `sink`.
+pub fn extract_all_to(
+    archive: &mut AlzArchive,
+    sink: &mut dyn ExtractSink,
+    opts: &ExtractOptions,
+) -> AlzResult<ExtractSummary> {
+    extract_all_report(archive, sink, opts)?.into_result()
+}
+
+/// Extract all entries into `sink` and report on each, e.g. for a GUI or CI
+/// job to show every outcome and, with [`ExtractOptions::keep_going`],
+/// every failure rather than just the first.
+pub fn extract_all_report(
+    archive: &mut AlzArchive,
+    sink: &mut dyn ExtractSink,
+    opts: &ExtractOptions,
+) -> AlzResult<ExtractReport> {
+    let (reader, entries) = archive.reader_and_entries();
+    let entries: Vec<_> = entries.iter().collect();
+    extract_entries_report(reader, &entries, sink, opts)
+}
+
+/// Default cap on the total bytes [`extract_to_map`] holds in memory.
+pub const MAP_SIZE_LIMIT: u64 = 1 << 30;
+
+/// Extract every file into memory, keyed by its normalized name
+/// (forward slashes, relative to the archive root).
+///
+/// Directories are left out and a symlink maps to the contents of the file
+/// it points to inside the archive; links to anything else are dropped.
+/// Each file is still checked against its declared size and CRC. At most
+/// [`MAP_SIZE_LIMIT`] bytes are kept in total; see [`extract_to_map_with`].
+pub fn extract_to_map(
+    archive: &mut AlzArchive,
+    password: Option<&str>,
+) -> AlzResult<BTreeMap<String, Vec<u8>>> {
+    extract_to_map_with(archive, password, MAP_SIZE_LIMIT)
+}
+
+/// [`extract_to_map`] holding at most `limit` bytes in total, symlink
+/// copies included. Output is also cut off at each entry's declared size,
+/// so a small entry that inflates far past its header fails with
+/// [`AlzError::SizeLimitExceeded`] instead of being buffered.
+pub fn extract_to_map_with(
+    archive: &mut AlzArchive,
+    password: Option<&str>,
+    limit: u64,
+) -> AlzResult<BTreeMap<String, Vec<u8>>> {
+    let opts = ExtractOptions {
+        password,
+        quiet: true,
+        ..Default::default()
+    };
+    let mut sink = MapSink {
+        inner: MemorySink::new(),
+        limit,
+        entry_left: 0,
+        total_left: limit,
+        exceeded: None,
+    };
+    let (reader, entries) = archive.reader_and_entries();
+    for entry in entries {
+        sink.entry_left = entry.uncompressed_size;
+        let result = extract_entry_to(reader, entry, &mut sink, &opts);
+        if let Some(limit) = sink.exceeded {
+            return Err(AlzError::SizeLimitExceeded {
+                name: entry.file_name.clone(),
+                limit,
+            });
         }
-        extract_entry(archive, entry, dest_dir, password, pipe_mode)?;
-        if !quiet && !pipe_mode {
-            eprint!(".. ok");
+        result?;
+    }
+    let MapSink {
+        inner: sink,
+        mut total_left,
+        ..
+    } = sink;
+    let mut map: BTreeMap<_, _> = sink
+        .files
+        .into_iter()
+        .map(|(path, data)| (map_key(&path), data))
+        .collect();
+    for link in sink.symlinks.keys() {
+        let Some(data) = resolve_link(&sink.symlinks, link).and_then(|p| map.get(&p)) else {
+            continue;
+        };
+        total_left = total_left.checked_sub(data.len() as u64).ok_or_else(|| {
+            AlzError::SizeLimitExceeded {
+                name: map_key(link),
+                limit,
+            }
+        })?;
+        map.insert(map_key(link), data.clone());
+    }
+    Ok(map)
+}
+
+/// A [`MemorySink`] whose files fail to grow past `entry_left` bytes each
+/// or `limit` bytes together, recording which cap was hit.
+struct MapSink {
+    inner: MemorySink,
+    limit: u64,
+    entry_left: u64,
+    total_left: u64,
+    exceeded: Option<u64>,
+}
+
+impl ExtractSink for MapSink {
+    fn create_dir(&mut self, path: &Path) -> AlzResult<()> {
+        self.inner.create_dir(path)
+    }
+
+    fn create_file(&mut self, path: &Path) -> AlzResult<Box<dyn Write + '_>> {
+        let buf = self.inner.files.entry(path.to_path_buf()).or_default();
+        buf.clear();
+        Ok(Box::new(MapWriter {
+            buf,
+            entry_limit: self.entry_left,
+            total_limit: self.limit,
+            total_left: &mut self.total_left,
+            exceeded: &mut self.exceeded,
+        }))
+    }
+
+    fn symlink(&mut self, target: &str, path: &Path) -> AlzResult<()> {
+        self.inner.symlink(target, path)
+    }
+
+    fn remove_file(&mut self, path: &Path) -> AlzResult<()> {
+        self.inner.remove_file(path)
+    }
+
+    fn rename(&mut self, from: &Path, to: &Path) -> AlzResult<()> {
+        self.inner.rename(from, to)
+    }
+}
+
+struct MapWriter<'a> {
+    buf: &'a mut Vec<u8>,
+    entry_limit: u64,
+    total_limit: u64,
+    total_left: &'a mut u64,
+    exceeded: &'a mut Option<u64>,
+}
+
+impl Write for MapWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = buf.len() as u64;
+        if self.buf.len() as u64 + len > self.entry_limit {
+            *self.exceeded = Some(self.entry_limit);
+        } else if len > *self.total_left {
+            *self.exceeded = Some(self.total_limit);
+        } else {
+            *self.total_left -= len;
+            self.buf.extend_from_slice(buf);
+            return Ok(buf.len());
         }
+        Err(io::Error::other("size limit exceeded"))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn map_key(path: &Path) -> String {
+    let parts: Vec<_> = path.iter().map(|c| c.to_string_lossy()).collect();
+    parts.join("/")
+}
+
+/// Follow `link` through `links` to a path that isn't one, giving up on
+/// cycles and targets outside the archive.
+fn resolve_link(links: &BTreeMap<PathBuf, String>, link: &Path) -> Option<String> {
+    let mut path = link.to_path_buf();
+    for _ in 0..=links.len() {
+        let Some(target) = links.get(&path) else {
+            return Some(map_key(&path));
+        };
+        let mut next = path.parent().unwrap_or(Path::new("")).to_path_buf();
+        for part in target.split(['/', '\\']) {
+            match part {
+                "" | "." => {}
+                ".." => {
+                    if !next.pop() {
+                        return None;
+                    }
+                }
+                _ => next.push(part),
+            }
+        }
+        path = next;
+    }
+    None
+}
+
+/// Extract exactly `entries`, in order, into `sink`.
+///
+/// The entries must come from the archive `reader` belongs to; use
+/// [`AlzArchive::reader_and_entries`] to select them while holding the
+/// reader, e.g. every Deflate entry over 1 MiB.
+pub fn extract_entries_to(
+    reader: &mut MultiVolumeReader,
+    entries: &[&AlzFileEntry],
+    sink: &mut dyn ExtractSink,
+    opts: &ExtractOptions,
+) -> AlzResult<ExtractSummary> {
+    extract_entries_report(reader, entries, sink, opts)?.into_result()
+}
+
+/// [`extract_entries_to`], returning the outcome of every entry and, with
+/// [`ExtractOptions::keep_going`], every failure.
+pub fn extract_entries_report(
+    reader: &mut MultiVolumeReader,
+    entries: &[&AlzFileEntry],
+    sink: &mut dyn ExtractSink,
+    opts: &ExtractOptions,
+) -> AlzResult<ExtractReport> {
+    let latest;
+    let entries = if opts.latest_only {
+        latest = latest_only(entries);
+        &latest[..]
+    } else {
+        entries
+    };
+    let mut report = ExtractReport::default();
+    let mut files = 0;
+    let mut done = entries.len();
+    for (i, entry) in entries.iter().enumerate() {
+        if opts.limit_reached(files) {
+            report.summary.limited = true;
+            done = i;
+            break;
+        }
+        let before = report.summary.entries;
+        extract_reporting(entry, opts, &mut report, || {
+            extract_entry_to(reader, entry, sink, opts)
+        })?;
+        if report.summary.entries > before && !entry.is_directory() {
+            files += 1;
+        }
+    }
+    let dirs = set_dir_mtimes(
+        entries[..done].iter().copied().filter(|e| e.is_directory()),
+        sink,
+        opts,
+    );
+    report.finish(dirs)
+}
+
+/// `entries` without those followed by a later entry of the same name
+/// (with either kind of slash), keeping the order.
+pub fn latest_only<'e>(entries: &[&'e AlzFileEntry]) -> Vec<&'e AlzFileEntry> {
+    let last: HashMap<_, _> = entries
+        .iter()
+        .enumerate()
+        .map(|(i, e)| (e.normalized_name(), i))
+        .collect();
+    entries
+        .iter()
+        .enumerate()
+        .filter(|&(i, e)| last[&e.normalized_name()] == i)
+        .map(|(_, e)| *e)
+        .collect()
+}
+
+/// The entry a file named on the command line selects: the first with
+/// exactly that name or, with `latest_only`, the last with that name under
+/// either kind of slash, matching [`latest_only`].
+pub fn find_named<'e>(
+    entries: &'e [AlzFileEntry],
+    name: &str,
+    latest_only: bool,
+) -> Option<&'e AlzFileEntry> {
+    if latest_only {
+        let name = name.replace('\\', "/");
+        entries.iter().rfind(|e| e.normalized_name() == name)
+    } else {
+        entries.iter().find(|e| e.file_name == name)
+    }
+}
+
+/// Extract exactly `entries`; see [`extract_entries_to`].
+pub fn extract_entries(
+    reader: &mut MultiVolumeReader,
+    entries: &[&AlzFileEntry],
+    dest_dir: &Path,
+    password: Option<&str>,
+    pipe_mode: bool,
+    quiet: bool,
+) -> AlzResult<()> {
+    let opts = ExtractOptions {
+        password,
+        quiet: quiet || pipe_mode,
+        ..Default::default()
+    };
+    if pipe_mode {
+        extract_entries_to(reader, entries, &mut PipeSink, &opts)?;
+    } else {
+        let mut sink = RealFsSink::new(dest_dir);
+        extract_entries_to(reader, entries, &mut sink, &opts)?;
     }
     Ok(())
 }
@@ -196,22 +1621,210 @@ pub fn extract_files(
     pipe_mode: bool,
     quiet: bool,
 ) -> AlzResult<()> {
-    let entries: Vec<AlzFileEntry> = archive.entries.clone();
+    let opts = ExtractOptions {
+        password,
+        quiet: quiet || pipe_mode,
+        ..Default::default()
+    };
+    if pipe_mode {
+        extract_files_to(archive, &mut PipeSink, file_names, &opts)?;
+    } else {
+        let mut sink = RealFsSink::new(dest_dir);
+        extract_files_to(archive, &mut sink, file_names, &opts)?;
+    }
+    Ok(())
+}
+
+/// Extract specific files by name into `sink`.
+pub fn extract_files_to(
+    archive: &mut AlzArchive,
+    sink: &mut dyn ExtractSink,
+    file_names: &[String],
+    opts: &ExtractOptions,
+) -> AlzResult<ExtractSummary> {
+    let (reader, entries) = archive.reader_and_entries();
+    let mut selected = Vec::new();
     for name in file_names {
-        if let Some(entry) = entries.iter().find(|e| e.file_name == *name) {
-            if !quiet && !pipe_mode {
-                eprint!(
-                    "\nextracting : {} ({}bytes) ",
-                    entry.file_name, entry.uncompressed_size
-                );
-            }
-            extract_entry(archive, entry, dest_dir, password, pipe_mode)?;
-            if !quiet && !pipe_mode {
-                eprint!(".. ok");
-            }
-        } else if !quiet && !pipe_mode {
+        if let Some(entry) = find_named(entries, name, opts.latest_only) {
+            selected.push(entry);
+        } else if !opts.quiet {
             eprintln!("\nfilename not matched : {name}");
         }
     }
+    extract_entries_to(reader, &selected, sink, opts)
+}
+
+/// Extract the entry at `index` in the archive's entry list.
+///
+/// Unlike name-based selection this is unambiguous when several entries
+/// share a name.
+pub fn extract_index(
+    archive: &mut AlzArchive,
+    index: usize,
+    dest_dir: &Path,
+    password: Option<&str>,
+    pipe_mode: bool,
+) -> AlzResult<()> {
+    let opts = ExtractOptions {
+        password,
+        quiet: true,
+        ..Default::default()
+    };
+    if pipe_mode {
+        extract_index_to(archive, index, &mut PipeSink, &opts)?;
+    } else {
+        extract_index_to(archive, index, &mut RealFsSink::new(dest_dir), &opts)?;
+    }
     Ok(())
 }
+
+/// Extract the entry at `index` into `sink`.
+pub fn extract_index_to(
+    archive: &mut AlzArchive,
+    index: usize,
+    sink: &mut dyn ExtractSink,
+    opts: &ExtractOptions,
+) -> AlzResult<EntryOutcome> {
+    let (reader, entries) = archive.reader_and_entries();
+    let entry = entries.get(index).ok_or(AlzError::NoSuchEntry(index))?;
+    extract_entry_to(reader, entry, sink, opts)
+}
+
+/// Run `extract` for one entry with progress output and
+/// [`ExtractOptions::on_entry`], adding the outcome to `report`. With
+/// `keep_going`, a failure is reported and added to `report.errors`
+/// instead of returned, and an unknown compression method only skips the
+/// entry.
+fn extract_reporting(
+    entry: &AlzFileEntry,
+    opts: &ExtractOptions,
+    report: &mut ExtractReport,
+    extract: impl FnOnce() -> AlzResult<EntryOutcome>,
+) -> AlzResult<()> {
+    if !opts.quiet {
+        eprint!(
+            "\nextracting : {} ({} bytes) ",
+            entry.file_name, entry.uncompressed_size
+        );
+    }
+    let result = extract();
+    if !opts.quiet {
+        match &result {
+            Ok(outcome) if outcome.skipped => eprint!(".. skipped"),
+            Ok(outcome) if crc_ignored(entry, opts, *outcome) => eprint!(
+                ".. ok (warning: CRC mismatch ignored, expected {:08x}, got {:08x})",
+                entry.file_crc, outcome.crc
+            ),
+            Ok(outcome) if outcome.mtime_failed => {
+                eprint!(".. ok (warning: modification time not set)")
+            }
+            Ok(_) => eprint!(".. ok"),
+            Err(AlzError::UnknownCompressionMethod(n)) if opts.keep_going => {
+                eprint!(".. skipped: unknown compression method {n}")
+            }
+            Err(e) if opts.keep_going && !matches!(e, AlzError::Cancelled) => {
+                eprint!(".. failed: {e}")
+            }
+            Err(_) => {}
+        }
+    }
+    if let Some(on_entry) = opts.on_entry {
+        on_entry(entry, &result);
+    }
+    match result {
+        Ok(outcome) => {
+            report.summary.add(outcome);
+            report.per_entry.push((entry.file_name.clone(), outcome));
+            Ok(())
+        }
+        Err(AlzError::UnknownCompressionMethod(_)) if opts.keep_going => {
+            let skipped = (entry.file_name.clone(), EntryOutcome::SKIPPED);
+            report.per_entry.push(skipped);
+            Ok(())
+        }
+        Err(e) if opts.keep_going && !matches!(e, AlzError::Cancelled) => {
+            report.errors.push((entry.file_name.clone(), e));
+            Ok(())
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Whether `outcome` is a file kept by [`ExtractOptions::ignore_crc`]
+/// despite a CRC mismatch. Directories and placeholders carry no CRC.
+fn crc_ignored(entry: &AlzFileEntry, opts: &ExtractOptions, outcome: EntryOutcome) -> bool {
+    opts.ignore_crc
+        && !opts.placeholders
+        && !entry.is_directory()
+        && entry.has_crc()
+        && outcome.crc != entry.file_crc
+}
+
+/// Parse and extract an archive in one forward pass over `reader`.
+///
+/// Unlike [`extract_all_to`], this never seeks, so it works on pipes and
+/// sockets. Each entry's data is extracted as its header is read. Without
+/// the file tail the comment section's size is unknown, so comment entries
+/// are skipped until the next record signature.
+pub fn extract_streaming<R: Read>(
+    reader: R,
+    sink: &mut dyn ExtractSink,
+    opts: &ExtractOptions,
+) -> AlzResult<ExtractSummary> {
+    let mut reader = CountingReader {
+        inner: reader,
+        pos: 0,
+    };
+    let mut parser = Parser::streaming();
+    let mut report = ExtractReport::default();
+    let mut dirs = Vec::new();
+    let mut files = 0;
+    while let Some(mut entry) = parser.next_header(&mut reader)? {
+        if opts.limit_reached(files) {
+            report.summary.limited = true;
+            break;
+        }
+        let data_len = if entry.size_unknown {
+            // Already scanned over by the parser.
+            entry.data_pos = reader
+                .pos
+                .checked_sub(parser.pending_len() + entry.compressed_size)
+                .ok_or(AlzError::CorruptedFile)?;
+            0
+        } else {
+            entry.data_pos = reader
+                .pos
+                .checked_sub(parser.pending_len())
+                .ok_or(AlzError::CorruptedFile)?;
+            entry.compressed_size
+        };
+        let mut limited = (&mut reader).take(data_len);
+        let before = report.summary.entries;
+        extract_reporting(&entry, opts, &mut report, || {
+            extract_entry_data(&mut limited, &entry, sink, opts)
+        })?;
+        // Consume whatever the decoder left so the next header is aligned.
+        io::copy(&mut limited, &mut io::sink())?;
+        if entry.is_directory() {
+            dirs.push(entry);
+        } else if report.summary.entries > before {
+            files += 1;
+        }
+    }
+    let dirs = set_dir_mtimes(&dirs, sink, opts);
+    report.finish(dirs)?.into_result()
+}
+
+/// Reader that tracks how many bytes have passed through it.
+struct CountingReader<R> {
+    inner: R,
+    pos: u64,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
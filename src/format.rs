@@ -0,0 +1,56 @@
+//! Archive format detection by leading magic bytes.
+
+use std::fmt;
+
+/// Archive formats recognized by [`detect`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Alz,
+    /// ALZip's newer format.
+    Egg,
+    Zip,
+    Rar,
+    Unknown,
+}
+
+impl fmt::Display for ArchiveFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Alz => "ALZ",
+            Self::Egg => "EGG",
+            Self::Zip => "ZIP",
+            Self::Rar => "RAR",
+            Self::Unknown => "unknown",
+        })
+    }
+}
+
+/// Identify an archive from its first bytes (four are enough).
+pub fn detect(bytes: &[u8]) -> ArchiveFormat {
+    if bytes.starts_with(b"ALZ\x01") {
+        ArchiveFormat::Alz
+    } else if bytes.starts_with(b"EGGA") {
+        ArchiveFormat::Egg
+    } else if bytes.starts_with(b"PK\x03\x04") {
+        ArchiveFormat::Zip
+    } else if bytes.starts_with(b"Rar!") {
+        ArchiveFormat::Rar
+    } else {
+        ArchiveFormat::Unknown
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(detect(b"ALZ\x01\x0a\x00"), ArchiveFormat::Alz);
+        assert_eq!(detect(b"EGGA\x00\x01"), ArchiveFormat::Egg);
+        assert_eq!(detect(b"PK\x03\x04"), ArchiveFormat::Zip);
+        assert_eq!(detect(b"Rar!\x1a\x07"), ArchiveFormat::Rar);
+        assert_eq!(detect(b"ALZ"), ArchiveFormat::Unknown);
+        assert_eq!(detect(b""), ArchiveFormat::Unknown);
+    }
+}
@@ -0,0 +1,138 @@
+//! `--interactive`: a small command loop over an archive that is parsed once.
+
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+
+use unalz_rs::archive::{AlzArchive, AlzFileEntry};
+use unalz_rs::dostime::dos_datetime_to_string;
+use unalz_rs::extract::{self, ExtractOptions};
+use unalz_rs::sink::{PipeSink, RealFsSink};
+
+use crate::{Listing, list_archive};
+
+const HELP: &str = "\
+commands:
+  ls                 list all entries
+  cat <name>         write an entry's contents to stdout
+  extract <pattern>  extract entries matching a glob pattern
+  info <name>        show an entry's header fields
+  help               show this message
+  quit               leave (also Ctrl-D)";
+
+/// Read commands from stdin until `quit` or end of input.
+pub fn run(archive: &mut AlzArchive, source: &str, dest_dir: &Path, opts: &ExtractOptions) {
+    eprintln!("{HELP}");
+    let mut lines = io::stdin().lock().lines();
+    loop {
+        eprint!("unalz> ");
+        io::stderr().flush().ok();
+        let Some(Ok(line)) = lines.next() else {
+            eprintln!();
+            break;
+        };
+        let line = line.trim();
+        let (command, arg) = line.split_once(' ').unwrap_or((line, ""));
+        let arg = arg.trim();
+        match command {
+            "" => {}
+            "ls" => list_archive(
+                archive.entries.iter().cloned().map(Ok),
+                source,
+                archive.volume_count(),
+                &Listing::default(),
+            ),
+            "cat" => cat(archive, arg, opts),
+            "extract" => extract(archive, arg, dest_dir, opts),
+            "info" => match find(archive, arg) {
+                Some(entry) => info(archive, entry),
+                None => eprintln!("err: no entry named {arg}"),
+            },
+            "help" | "?" => eprintln!("{HELP}"),
+            "quit" | "exit" | "q" => break,
+            _ => eprintln!("err: unknown command {command:?}; try help"),
+        }
+    }
+}
+
+/// Look up an entry by name, with either kind of slash.
+fn find<'e>(archive: &'e AlzArchive, name: &str) -> Option<&'e AlzFileEntry> {
+    let name = name.replace('\\', "/");
+    archive.iter().find(|e| e.normalized_name() == name)
+}
+
+fn cat(archive: &mut AlzArchive, name: &str, opts: &ExtractOptions) {
+    let Some(entry) = find(archive, name).cloned() else {
+        eprintln!("err: no entry named {name}");
+        return;
+    };
+    let opts = ExtractOptions {
+        quiet: true,
+        ..*opts
+    };
+    let (reader, _) = archive.reader_and_entries();
+    if let Err(e) = extract::extract_entries_to(reader, &[&entry], &mut PipeSink, &opts) {
+        eprintln!("err: {e}");
+    }
+    io::stdout().flush().ok();
+}
+
+fn extract(archive: &mut AlzArchive, pattern: &str, dest_dir: &Path, opts: &ExtractOptions) {
+    let pattern = match glob::Pattern::new(pattern) {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("err: invalid glob pattern: {e}");
+            return;
+        }
+    };
+    let (reader, entries) = archive.reader_and_entries();
+    let selected: Vec<_> = entries
+        .iter()
+        .filter(|e| pattern.matches(&e.normalized_name()))
+        .collect();
+    if selected.is_empty() {
+        eprintln!("err: no entry matches {pattern}");
+        return;
+    }
+    let mut sink = RealFsSink::new(dest_dir);
+    match extract::extract_entries_to(reader, &selected, &mut sink, opts) {
+        Ok(summary) => eprintln!(
+            "\n{} entries, {} bytes to {}",
+            summary.entries,
+            summary.bytes,
+            dest_dir.display()
+        ),
+        Err(e) => eprintln!("\nerr: {e}"),
+    }
+}
+
+fn info(archive: &AlzArchive, entry: &AlzFileEntry) {
+    println!("name:         {}", entry.file_name);
+    println!(
+        "type:         {}",
+        if entry.is_directory() {
+            "directory"
+        } else if entry.is_symlink() {
+            "symlink"
+        } else {
+            "file"
+        }
+    );
+    println!("method:       {}", entry.compression_method);
+    println!("size:         {}", entry.uncompressed_size);
+    println!("compressed:   {}", entry.compressed_size);
+    println!("crc32:        {:08x}", entry.file_crc);
+    println!(
+        "modified:     {}",
+        dos_datetime_to_string(entry.file_time_date)
+    );
+    println!("attributes:   0x{:02x}", entry.file_attribute);
+    println!("descriptor:   0x{:02x}", entry.file_descriptor);
+    println!(
+        "encrypted:    {}",
+        if entry.is_encrypted() { "yes" } else { "no" }
+    );
+    println!("data offset:  {}", entry.data_pos);
+    if archive.volume_count() > 1 {
+        println!("volumes:      {:?}", archive.entry_volumes(entry));
+    }
+}
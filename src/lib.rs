@@ -5,4 +5,7 @@ pub mod dostime;
 pub mod encoding;
 pub mod error;
 pub mod extract;
+pub mod format;
 pub mod multivolume;
+pub mod selftest;
+pub mod sink;
@@ -1,21 +1,65 @@
 use std::io::{Read, Write};
 use std::path::Path;
 use std::process;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use clap::Parser;
 
 use unalz_rs::archive::{
     ATTR_ARCHIVE, ATTR_DIRECTORY, ATTR_HIDDEN, ATTR_READONLY, ATTR_SYMLINK, AlzArchive,
+    AlzFileEntry, DEFAULT_SFX_SCAN_LIMIT, ParseOptions,
 };
-use unalz_rs::dostime::dos_datetime_to_string;
+use unalz_rs::dostime::{DosTimeZone, dos_datetime_to_string};
+use unalz_rs::encoding::NameDecoding;
+#[cfg(feature = "normalize")]
+use unalz_rs::encoding::NameNormalization;
+use unalz_rs::error::{AlzError, AlzResult};
 use unalz_rs::extract;
+use unalz_rs::multivolume::MultiVolumeReader;
+use unalz_rs::sink::{ExtractSink, PipeSink, RealFsSink};
+
+#[cfg(feature = "interactive")]
+mod interactive;
+
+/// Exit status after Ctrl-C, as for a shell job killed by SIGINT.
+const EXIT_CANCELLED: i32 = 130;
+
+/// Set by the first Ctrl-C; extraction stops at the next chunk.
+static CANCEL: AtomicBool = AtomicBool::new(false);
+
+/// Stop extraction cleanly on Ctrl-C, removing the partial file. A second
+/// Ctrl-C exits at once.
+fn handle_ctrl_c() {
+    let _ = ctrlc::set_handler(|| {
+        if CANCEL.swap(true, Ordering::Relaxed) {
+            process::exit(EXIT_CANCELLED);
+        }
+    });
+}
 
 #[derive(Parser)]
 #[command(name = "unalz", about = "ALZ archive extractor", version = env!("CARGO_PKG_VERSION"))]
 struct Cli {
-    /// List contents of archive
-    #[arg(short = 'l', long = "list")]
-    list: bool,
+    /// List contents of archive (-ll for the long listing)
+    #[arg(short = 'l', long = "list", action = clap::ArgAction::Count)]
+    list: u8,
+
+    /// List with CRC, data offset, descriptor and method byte columns
+    #[arg(long = "long")]
+    long: bool,
+
+    /// Print only archive totals (files, sizes, ratio, encryption, volumes)
+    #[arg(long = "summary", alias = "count", conflicts_with_all = ["list", "long"])]
+    summary: bool,
+
+    /// List only encrypted entries
+    #[arg(long = "list-encrypted-only")]
+    list_encrypted_only: bool,
+
+    /// List only directories
+    #[arg(long = "list-dirs")]
+    list_dirs: bool,
 
     /// Extract files to pipe (stdout), suppress messages
     #[arg(short = 'p')]
@@ -25,16 +69,138 @@ struct Cli {
     #[arg(short = 'q', long = "quiet")]
     quiet: bool,
 
+    /// Show a percentage bar on stderr instead of one line per entry
+    #[arg(long = "progress")]
+    progress: bool,
+
+    /// Also show each file's compression method, sizes, ratio and CRC
+    #[arg(short = 'v', long = "verbose")]
+    verbose: bool,
+
+    /// Print one tab-separated line per entry on stdout for scripts
+    /// (EXTRACT, SKIP or ERROR, the name, then the size or error)
+    #[arg(long = "porcelain", conflicts_with_all = ["pipe", "progress", "batch"])]
+    porcelain: bool,
+
     /// Set output directory
     #[arg(short = 'd', value_name = "DIR")]
     dest_dir: Option<String>,
 
+    /// Place extracted entries under this subdirectory of the output directory
+    #[arg(long = "prefix", value_name = "SUBDIR")]
+    prefix: Option<String>,
+
+    /// Decode file names with this codepage (cp949, shift_jis, gbk, utf-8, ...)
+    #[arg(long = "codepage", value_name = "NAME")]
+    codepage: Option<String>,
+
+    /// Normalize decoded file names to this Unicode form (nfc, nfd); also
+    /// applies to the file names given on the command line
+    #[cfg(feature = "normalize")]
+    #[arg(long = "normalize", value_name = "FORM")]
+    normalize: Option<String>,
+
+    /// Time zone the archive's file times were recorded in: a fixed offset
+    /// (e.g. +09:00) or `local` for this machine's zone, with daylight
+    /// saving time; they are taken as UTC by default
+    #[arg(
+        long = "time-offset",
+        value_name = "OFFSET",
+        allow_hyphen_values = true
+    )]
+    time_offset: Option<String>,
+
+    /// Fail when a file's modification time cannot be set instead of warning
+    #[arg(long = "strict-times")]
+    strict_times: bool,
+
+    /// Fail on archives that end without an end record (likely truncated)
+    /// instead of warning
+    #[arg(long = "strict-end")]
+    strict_end: bool,
+
     /// Set password
     #[arg(long = "pwd", value_name = "PASSWORD")]
     password: Option<String>,
 
+    /// On CRC mismatch, keep the output as <name>.corrupt instead of deleting it
+    #[arg(long = "keep-corrupt")]
+    keep_corrupt: bool,
+
+    /// Keep files whose CRC does not match, with a warning (for archivers
+    /// known to write wrong CRCs)
+    #[arg(long = "ignore-crc", conflicts_with = "keep_corrupt")]
+    ignore_crc: bool,
+
+    /// Escape control characters in file names instead of failing
+    #[arg(long = "sanitize")]
+    sanitize: bool,
+
+    /// Only extract files that start with an image signature (PNG, JPEG, GIF, BMP, WebP)
+    #[arg(long = "images-only")]
+    images_only: bool,
+
+    /// Skip files smaller than BYTES
+    #[arg(long = "min-size", value_name = "BYTES")]
+    min_size: Option<u64>,
+
+    /// Skip files larger than BYTES
+    #[arg(long = "max-size", value_name = "BYTES")]
+    max_size: Option<u64>,
+
+    /// Stop after extracting N files
+    #[arg(long = "limit", value_name = "N")]
+    limit: Option<usize>,
+
+    /// Of entries sharing a name, extract only the last one in the archive
+    #[arg(long = "latest-only")]
+    latest_only: bool,
+
+    /// Create files at their full size without extracting their contents
+    #[arg(long = "placeholders")]
+    placeholders: bool,
+
+    /// Skip files already extracted with the right size and time
+    #[arg(long = "resume")]
+    resume: bool,
+
+    /// Like --resume, but also check the CRC of existing files
+    #[arg(long = "resume-verify")]
+    resume_verify: bool,
+
+    /// Continue extracting after an entry fails; skip unknown compression methods
+    #[arg(long = "keep-going")]
+    keep_going: bool,
+
+    /// Save entries with an unknown compression method, still compressed, as <name>.raw
+    #[arg(long = "keep-unknown-raw")]
+    keep_unknown_raw: bool,
+
+    /// Extract the entry at this position (0-based, as listed); repeatable
+    #[arg(long = "index", value_name = "N", conflicts_with = "files")]
+    index: Vec<usize>,
+
+    /// Treat every argument as an archive path or glob and extract each into
+    /// DIR/<archive name>/
+    #[arg(long = "batch", conflicts_with_all = ["index", "list"])]
+    batch: bool,
+
+    /// Open the archive once and read commands (ls, cat, extract, info)
+    /// from stdin
+    #[cfg(feature = "interactive")]
+    #[arg(
+        long = "interactive",
+        conflicts_with_all = ["list", "summary", "batch", "pipe", "porcelain", "progress"]
+    )]
+    interactive: bool,
+
+    /// Run built-in known-answer tests and exit
+    #[arg(long = "self-test")]
+    self_test: bool,
+
     /// Archive file (.alz), or "-" for stdin
-    archive: String,
+    #[arg(required_unless_present = "self_test")]
+    archive: Option<String>,
 
     /// Files to extract (if empty, extract all)
     files: Vec<String>,
@@ -43,19 +209,92 @@ struct Cli {
 fn main() {
     let cli = Cli::parse();
 
-    let quiet = cli.quiet || cli.pipe;
+    if cli.self_test {
+        match unalz_rs::selftest::run_self_test() {
+            Ok(()) => println!("self-test passed"),
+            Err(e) => {
+                eprintln!("self-test failed: {e}");
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
+    #[cfg(feature = "normalize")]
+    let normalization = match cli.normalize.as_deref() {
+        None => NameNormalization::None,
+        Some(form) => NameNormalization::from_label(form).unwrap_or_else(|| {
+            eprintln!("err: unknown normalization form: {form}");
+            process::exit(1);
+        }),
+    };
+    #[cfg(feature = "normalize")]
+    let cli = Cli {
+        files: cli.files.iter().map(|f| normalization.apply(f)).collect(),
+        ..cli
+    };
+
+    let source = cli.archive.as_deref().expect("archive is required");
+    let parse_opts = ParseOptions {
+        name_decoding: match cli.codepage.as_deref() {
+            None => NameDecoding::default(),
+            Some(name) => NameDecoding::from_label(name).unwrap_or_else(|| {
+                eprintln!("err: unknown codepage: {name}");
+                process::exit(1);
+            }),
+        },
+        require_end_record: cli.strict_end,
+        sfx_scan_limit: Some(DEFAULT_SFX_SCAN_LIMIT),
+        #[cfg(feature = "normalize")]
+        normalization,
+    };
+    let time_zone = match cli.time_offset.as_deref() {
+        None => DosTimeZone::Utc,
+        Some(offset) => DosTimeZone::parse(offset).unwrap_or_else(|| {
+            eprintln!("err: invalid time offset: {offset}");
+            process::exit(1);
+        }),
+    };
+    let quiet = cli.quiet || cli.pipe || cli.porcelain;
+    let list = cli.list > 0 || cli.long || cli.list_encrypted_only || cli.list_dirs;
+    let listing = Listing {
+        long: cli.list > 1 || cli.long,
+        encrypted_only: cli.list_encrypted_only,
+        dirs_only: cli.list_dirs,
+    };
 
     if !quiet {
         eprintln!("unalz-rs v{}", env!("CARGO_PKG_VERSION"));
     }
 
-    let mut archive = if cli.archive == "-" {
+    // List directly from the header stream so output starts immediately.
+    if list && source != "-" {
+        match AlzArchive::open_streaming_with(source, &parse_opts) {
+            Ok(stream) => {
+                let volumes = stream.volume_count();
+                list_archive(stream, source, volumes, &listing)
+            }
+            Err(e) => {
+                eprintln!("file open error : {source}");
+                eprintln!("err: {e}");
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if cli.batch {
+        extract_batch(&cli, quiet, time_zone);
+        return;
+    }
+
+    let mut archive = if source == "-" {
         let mut data = Vec::new();
         if let Err(e) = std::io::stdin().read_to_end(&mut data) {
             eprintln!("err: {e}");
             process::exit(1);
         }
-        match AlzArchive::from_bytes(data) {
+        match AlzArchive::from_reader_with(MultiVolumeReader::from_bytes(data), &parse_opts) {
             Ok(a) => a,
             Err(e) => {
                 eprintln!("file open error : stdin");
@@ -64,26 +303,46 @@ fn main() {
             }
         }
     } else {
-        match AlzArchive::open(&cli.archive) {
+        match MultiVolumeReader::open(source)
+            .and_then(|reader| AlzArchive::from_reader_with(reader, &parse_opts))
+        {
             Ok(a) => a,
             Err(e) => {
-                eprintln!("file open error : {}", cli.archive);
+                eprintln!("file open error : {source}");
                 eprintln!("err: {e}");
                 process::exit(1);
             }
         }
     };
 
-    if cli.list {
-        list_archive(&archive, &cli.archive);
+    if cli.summary {
+        print_summary(&archive, source);
+        return;
+    }
+
+    if list {
+        let volumes = archive.volume_count();
+        list_archive(
+            archive.entries.into_iter().map(Ok),
+            source,
+            volumes,
+            &listing,
+        );
         return;
     }
 
-    // Handle password.
-    let password = if archive.is_encrypted {
+    for feature in archive.unsupported_features() {
+        eprintln!("warning: unsupported feature: {feature}");
+    }
+    if !archive.end_record_seen {
+        eprintln!("warning: archive ends without an end record; it may be truncated");
+    }
+
+    // Handle password; only ask when a selected entry is encrypted.
+    let password = if needs_password(&archive, &cli) {
         if let Some(ref pwd) = cli.password {
             Some(pwd.clone())
-        } else if cli.archive == "-" {
+        } else if source == "-" {
             eprintln!("err: encrypted archive from stdin requires --pwd");
             process::exit(1);
         } else {
@@ -101,35 +360,105 @@ fn main() {
     let dest_dir = cli.dest_dir.as_deref().unwrap_or(".");
     let dest_path = Path::new(dest_dir);
 
+    #[cfg(feature = "interactive")]
+    if cli.interactive {
+        if source == "-" {
+            eprintln!("err: --interactive reads commands from stdin; pass an archive path");
+            process::exit(1);
+        }
+        let opts = extract::ExtractOptions {
+            password: password.as_deref(),
+            quiet: cli.quiet,
+            sanitize: cli.sanitize,
+            time_zone,
+            ..Default::default()
+        };
+        interactive::run(&mut archive, source, dest_path, &opts);
+        return;
+    }
+
     if !quiet {
-        eprintln!("\nExtract {} to {}", cli.archive, dest_dir);
+        eprintln!("\nExtract {source} to {dest_dir}");
     }
 
-    let result = if cli.files.is_empty() {
-        extract::extract_all(
-            &mut archive,
-            dest_path,
-            password.as_deref(),
-            cli.pipe,
-            quiet,
-        )
+    handle_ctrl_c();
+    // The total is set once the options it depends on exist.
+    let bar = cli.progress.then(ProgressBar::default);
+    let report = |entry: &AlzFileEntry, written: u64, _crc: u32| {
+        if let Some(bar) = &bar {
+            bar.update(entry, written);
+        }
+    };
+    let opts = extract::ExtractOptions {
+        password: password.as_deref(),
+        quiet: quiet || cli.progress,
+        progress: cli.progress.then_some(&report as _),
+        on_entry: if cli.porcelain {
+            Some(&porcelain_line)
+        } else if cli.verbose && !quiet && !cli.progress && !cli.placeholders {
+            Some(&verbose_details)
+        } else {
+            None
+        },
+        keep_corrupt: cli.keep_corrupt,
+        ignore_crc: cli.ignore_crc,
+        keep_going: cli.keep_going,
+        keep_unknown_raw: cli.keep_unknown_raw,
+        resume: cli.resume || cli.resume_verify,
+        resume_verify: cli.resume_verify,
+        sanitize: cli.sanitize,
+        prefix: cli.prefix.as_deref(),
+        time_zone,
+        strict_times: cli.strict_times,
+        min_size: cli.min_size,
+        max_size: cli.max_size,
+        limit: cli.limit,
+        latest_only: cli.latest_only,
+        placeholders: cli.placeholders,
+        content_filter: cli.images_only.then_some(&extract::is_image as _),
+        cancel: Some(&CANCEL),
+        ..Default::default()
+    };
+    if let Some(bar) = &bar {
+        bar.set_total(planned_size(&archive, &cli, &opts));
+    }
+    let result = if cli.pipe {
+        extract_with(&mut archive, &mut PipeSink, &cli, &opts)
     } else {
-        extract::extract_files(
-            &mut archive,
-            dest_path,
-            &cli.files,
-            password.as_deref(),
-            cli.pipe,
-            quiet,
-        )
+        extract_with(&mut archive, &mut RealFsSink::new(dest_path), &cli, &opts)
     };
 
+    if let (Some(bar), Ok(_)) = (&bar, &result) {
+        // Entries skipped by --images-only or --resume never report
+        // progress; the bar is full once extraction has finished.
+        bar.finish();
+        // End the bar's line; "done." and errors bring their own newline.
+        if quiet {
+            eprintln!();
+        }
+    }
+
     match result {
-        Ok(()) => {
+        Ok(summary) => {
+            if summary.limited && !cli.quiet {
+                eprintln!(
+                    "\nstopped after {} files (--limit)",
+                    cli.limit.unwrap_or_default()
+                );
+            }
             if !quiet {
                 eprintln!("\ndone.");
+            } else if cli.pipe && !cli.quiet {
+                eprintln!(
+                    "piped {} bytes from {} entries",
+                    summary.bytes, summary.entries
+                );
             }
         }
+        Err(AlzError::Cancelled) => {
+            eprintln!("\nextraction cancelled; files extracted so far were kept.");
+            process::exit(EXIT_CANCELLED);
+        }
         Err(e) => {
             eprintln!("\nextract failed: {e}");
             process::exit(1);
@@ -137,19 +466,334 @@ fn main() {
     }
 }
 
-fn list_archive(archive: &AlzArchive, source: &str) {
-    println!("\nListing archive: {source}");
-    println!();
-    println!("Attr   Uncomp Size    Comp Size Method  Date & Time & File Name");
+/// `--verbose` details, appended to the library's line for each entry.
+fn verbose_details(entry: &AlzFileEntry, result: &AlzResult<extract::EntryOutcome>) {
+    let Ok(outcome) = result else {
+        return;
+    };
+    if outcome.skipped || entry.is_directory() {
+        return;
+    }
+    let ratio = if entry.uncompressed_size > 0 {
+        format!(
+            ", {:.1}%",
+            entry.compressed_size as f64 * 100.0 / entry.uncompressed_size as f64
+        )
+    } else {
+        String::new()
+    };
+    let encrypted = if entry.is_encrypted() {
+        ", encrypted"
+    } else {
+        ""
+    };
+    eprint!(
+        " [{}, {} -> {} bytes{ratio}, CRC {:08x}{encrypted}]",
+        entry.compression_method, entry.compressed_size, outcome.size, outcome.crc
+    );
+}
+
+/// `--porcelain` output for one entry.
+fn porcelain_line(entry: &AlzFileEntry, result: &AlzResult<extract::EntryOutcome>) {
+    let name = &entry.file_name;
+    match result {
+        Ok(outcome) if outcome.skipped => println!("SKIP\t{name}"),
+        Ok(outcome) => println!("EXTRACT\t{name}\t{}", outcome.size),
+        Err(e) => println!("ERROR\t{name}\t{e}"),
+    }
+}
+
+/// `--batch`: extract each archive matching the arguments, continuing past
+/// failures, and exit with status 1 if any archive failed.
+fn extract_batch(cli: &Cli, quiet: bool, time_zone: DosTimeZone) {
+    let dest_path = Path::new(cli.dest_dir.as_deref().unwrap_or("."));
+    handle_ctrl_c();
+    let opts = extract::ExtractOptions {
+        password: cli.password.as_deref(),
+        quiet: true,
+        keep_corrupt: cli.keep_corrupt,
+        ignore_crc: cli.ignore_crc,
+        keep_going: cli.keep_going,
+        keep_unknown_raw: cli.keep_unknown_raw,
+        resume: cli.resume || cli.resume_verify,
+        resume_verify: cli.resume_verify,
+        sanitize: cli.sanitize,
+        prefix: cli.prefix.as_deref(),
+        time_zone,
+        strict_times: cli.strict_times,
+        min_size: cli.min_size,
+        max_size: cli.max_size,
+        limit: cli.limit,
+        latest_only: cli.latest_only,
+        placeholders: cli.placeholders,
+        content_filter: cli.images_only.then_some(&extract::is_image as _),
+        cancel: Some(&CANCEL),
+        ..Default::default()
+    };
+    let mut failed = false;
+    for pattern in cli.archive.iter().chain(&cli.files) {
+        let results = match extract::extract_glob(pattern, dest_path, &opts) {
+            Ok(results) => results,
+            Err(e) => {
+                eprintln!("err: {e}");
+                failed = true;
+                continue;
+            }
+        };
+        if results.is_empty() {
+            eprintln!("err: no archive matches {pattern}");
+            failed = true;
+        }
+        for archive in results {
+            let path = archive.path.display();
+            match archive.result {
+                Ok(summary) if !quiet => eprintln!(
+                    "{path}: {} entries, {} bytes{}",
+                    summary.entries,
+                    summary.bytes,
+                    if summary.limited { " (limited)" } else { "" }
+                ),
+                Ok(_) => {}
+                Err(AlzError::Cancelled) => {
+                    eprintln!("{path}: extraction cancelled");
+                    process::exit(EXIT_CANCELLED);
+                }
+                Err(e) => {
+                    eprintln!("{path}: err: {e}");
+                    failed = true;
+                }
+            }
+        }
+    }
+    if failed {
+        process::exit(1);
+    }
+}
+
+fn extract_with(
+    archive: &mut AlzArchive,
+    sink: &mut dyn ExtractSink,
+    cli: &Cli,
+    opts: &extract::ExtractOptions,
+) -> AlzResult<extract::ExtractSummary> {
+    if !cli.index.is_empty() {
+        let (reader, entries) = archive.reader_and_entries();
+        let selected = cli
+            .index
+            .iter()
+            .map(|&i| entries.get(i).ok_or(AlzError::NoSuchEntry(i)))
+            .collect::<AlzResult<Vec<_>>>()?;
+        extract::extract_entries_to(reader, &selected, sink, opts)
+    } else if cli.files.is_empty() {
+        extract::extract_all_to(archive, sink, opts)
+    } else {
+        extract::extract_files_to(archive, sink, &cli.files, opts)
+    }
+}
+
+/// Files (not directories) the command line selects, within the size
+/// limits.
+fn selected_files<'e>(archive: &'e AlzArchive, cli: &Cli) -> Vec<&'e AlzFileEntry> {
+    let entries: Vec<_> = if !cli.index.is_empty() {
+        let picked: Vec<_> = cli
+            .index
+            .iter()
+            .filter_map(|&i| archive.entries.get(i))
+            .collect();
+        if cli.latest_only {
+            extract::latest_only(&picked)
+        } else {
+            picked
+        }
+    } else if cli.files.is_empty() {
+        let all: Vec<_> = archive.entries.iter().collect();
+        if cli.latest_only {
+            extract::latest_only(&all)
+        } else {
+            all
+        }
+    } else {
+        cli.files
+            .iter()
+            .filter_map(|name| extract::find_named(&archive.entries, name, cli.latest_only))
+            .collect()
+    };
+    entries
+        .into_iter()
+        .filter(|e| {
+            !e.is_directory()
+                && cli.min_size.is_none_or(|min| e.uncompressed_size >= min)
+                && cli.max_size.is_none_or(|max| e.uncompressed_size <= max)
+        })
+        .collect()
+}
+
+/// Whether any entry the command line selects is encrypted.
+fn needs_password(archive: &AlzArchive, cli: &Cli) -> bool {
+    !cli.placeholders
+        && selected_files(archive, cli)
+            .iter()
+            .any(|e| e.is_encrypted())
+}
+
+/// Bytes the selected entries will extract to with `opts`; see
+/// [`AlzArchive::extraction_size`].
+fn planned_size(archive: &AlzArchive, cli: &Cli, opts: &extract::ExtractOptions) -> u64 {
+    if cli.index.is_empty() {
+        let files = (!cli.files.is_empty()).then_some(&cli.files[..]);
+        return archive.extraction_size(files, opts);
+    }
+    selected_files(archive, cli)
+        .iter()
+        .take(opts.limit.unwrap_or(usize::MAX))
+        .fold(0u64, |acc, e| acc.saturating_add(e.uncompressed_size))
+}
+
+/// Overall percentage bar drawn on stderr from the per-entry progress
+/// callback.
+#[derive(Default)]
+struct ProgressBar {
+    state: Mutex<BarState>,
+}
+
+#[derive(Default)]
+struct BarState {
+    /// Bytes the whole extraction is expected to write.
+    total: u64,
+    /// Bytes written by entries before the current one.
+    done: u64,
+    /// `data_pos` of the entry being written, to tell entries apart.
+    entry_pos: Option<u64>,
+    /// Bytes written so far by the current entry.
+    entry_written: u64,
+    /// Last percentage drawn.
+    shown: Option<u64>,
+}
+
+impl ProgressBar {
+    const WIDTH: u64 = 40;
+
+    fn set_total(&self, total: u64) {
+        self.state().total = total;
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, BarState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn update(&self, entry: &AlzFileEntry, written: u64) {
+        let mut state = self.state();
+        if state.entry_pos != Some(entry.data_pos) {
+            state.done = state.done.saturating_add(state.entry_written);
+            state.entry_pos = Some(entry.data_pos);
+            state.entry_written = 0;
+        }
+        state.entry_written = written;
+        let current = state.done.saturating_add(written).min(state.total);
+        Self::draw(&mut state, current);
+    }
+
+    /// Draw the bar full.
+    fn finish(&self) {
+        let mut state = self.state();
+        let total = state.total;
+        Self::draw(&mut state, total);
+    }
+
+    fn draw(state: &mut BarState, current: u64) {
+        let percent = match state.total {
+            0 => 100,
+            total => (current as u128 * 100 / total as u128) as u64,
+        };
+        // Redraw only when the number changes.
+        if state.shown == Some(percent) {
+            return;
+        }
+        state.shown = Some(percent);
+        let filled = (percent * Self::WIDTH / 100) as usize;
+        eprint!(
+            "\r[{}{}] {percent:>3}% {current}/{} bytes",
+            "#".repeat(filled),
+            "-".repeat(Self::WIDTH as usize - filled),
+            state.total,
+        );
+        std::io::stderr().flush().ok();
+    }
+}
+
+/// Listing format and row filters.
+#[derive(Default)]
+struct Listing {
+    long: bool,
+    encrypted_only: bool,
+    dirs_only: bool,
+}
+
+impl Listing {
+    fn shows(&self, entry: &AlzFileEntry) -> bool {
+        (!self.encrypted_only || entry.is_encrypted()) && (!self.dirs_only || entry.is_directory())
+    }
+}
+
+fn print_summary(archive: &AlzArchive, source: &str) {
+    let uncompressed = archive.total_uncompressed_size();
+    let compressed = archive.total_compressed_size();
+    println!("\nArchive: {source}");
+    println!("Files:             {}", archive.file_count());
+    println!("Uncompressed size: {uncompressed}");
+    println!("Compressed size:   {compressed}");
+    if uncompressed > 0 {
+        let ratio = compressed as f64 / uncompressed as f64 * 100.0;
+        println!("Ratio:             {ratio:.1}%");
+    }
     println!(
-        "----- ------------ ------------ ------- ------------------------------------------------"
+        "Encrypted:         {}",
+        if archive.is_encrypted { "yes" } else { "no" }
     );
+    println!("Volumes:           {}", archive.volume_count());
+}
+
+fn list_archive<I: Iterator<Item = AlzResult<AlzFileEntry>>>(
+    entries: I,
+    source: &str,
+    volumes: usize,
+    listing: &Listing,
+) {
+    let long = listing.long;
+    let (header, separator) = if long {
+        (
+            "Attr   Uncomp Size    Comp Size Method  Date & Time          CRC32       DataPos Desc Mth File Name",
+            "----- ------------ ------------ ------- -------------------  -------- ---------- ---- --- ----------------",
+        )
+    } else {
+        (
+            "Attr   Uncomp Size    Comp Size Method  Date & Time & File Name",
+            "----- ------------ ------------ ------- ------------------------------------------------",
+        )
+    };
+    println!("\nListing archive: {source}");
+    if volumes > 1 {
+        println!("{volumes}-volume archive.");
+    }
+    println!();
+    println!("{header}");
+    println!("{separator}");
 
     let mut total_uncompressed: u64 = 0;
     let mut total_compressed: u64 = 0;
     let mut file_count: u32 = 0;
 
-    for entry in &archive.entries {
+    for entry in entries {
+        let entry = match entry {
+            Ok(e) => e,
+            Err(e) => {
+                eprintln!("err: {e}");
+                process::exit(1);
+            }
+        };
+        if !listing.shows(&entry) {
+            continue;
+        }
         let a = entry.file_attribute;
         let attr = format!(
             "{}{}{}{}{}",
@@ -162,9 +806,20 @@ fn list_archive(archive: &AlzArchive, source: &str) {
 
         let datetime = dos_datetime_to_string(entry.file_time_date);
         let encrypted = if entry.is_encrypted() { "*" } else { "" };
+        let extra = if long {
+            format!(
+                "{:08x} {:>10} 0x{:02x} {:>3} ",
+                entry.file_crc,
+                entry.data_pos,
+                entry.file_descriptor,
+                entry.compression_method.as_byte(),
+            )
+        } else {
+            String::new()
+        };
 
         println!(
-            "{attr} {:>12} {:>12} {:<7} {datetime}  {}{encrypted}",
+            "{attr} {:>12} {:>12} {:<7} {datetime:<20} {extra}{}{encrypted}",
             entry.uncompressed_size,
             entry.compressed_size,
             entry.compression_method,
@@ -172,13 +827,12 @@ fn list_archive(archive: &AlzArchive, source: &str) {
         );
 
         file_count += 1;
-        total_uncompressed += entry.uncompressed_size;
-        total_compressed += entry.compressed_size;
+        // Saturate: a crafted archive can claim sizes whose sum overflows.
+        total_uncompressed = total_uncompressed.saturating_add(entry.uncompressed_size);
+        total_compressed = total_compressed.saturating_add(entry.compressed_size);
     }
 
-    println!(
-        "----- ------------ ------------ ------- ------------------------------------------------"
-    );
+    println!("{separator}");
     let plural = if file_count <= 1 { "" } else { "s" };
     println!(
         "      {total_uncompressed:>12} {total_compressed:>12}         Total {file_count} file{plural}"
@@ -1,41 +1,134 @@
 use std::fs::File;
-use std::io::{self, Read, Seek, SeekFrom};
-use std::path::Path;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use crate::error::{AlzError, AlzResult};
 
-trait ReadSeek: Read + Seek {}
+/// A readable, seekable volume.
+pub trait ReadSeek: Read + Seek {}
 impl<T: Read + Seek> ReadSeek for T {}
 
+/// Supplies the volumes of an archive by index (0 = `.alz`, 1 = `.a00`, ...).
+///
+/// Implement this to read volumes from somewhere other than local files,
+/// e.g. HTTP range requests. Every volume is opened (and its length taken
+/// with `Seek`) when the reader is created, but data is only read on demand.
+pub trait VolumeSource {
+    /// Open volume `index`, or return `None` if there is no such volume.
+    fn open_volume(&mut self, index: usize) -> io::Result<Option<Box<dyn ReadSeek>>>;
+}
+
+impl<F: FnMut(usize) -> Option<Box<dyn ReadSeek>>> VolumeSource for F {
+    fn open_volume(&mut self, index: usize) -> io::Result<Option<Box<dyn ReadSeek>>> {
+        Ok(self(index))
+    }
+}
+
 const MAX_VOLUMES: usize = 1000;
 const VOLUME_HEADER_SIZE: u64 = 8;
 const VOLUME_TRAILER_SIZE: u64 = 16;
 
-struct Volume {
-    file: Box<dyn ReadSeek>,
+struct Volume<'a> {
+    file: Box<dyn ReadSeek + 'a>,
     file_size: u64,
     header_size: u64,
     tail_size: u64,
 }
 
-impl Volume {
+impl Volume<'_> {
     fn data_size(&self) -> u64 {
-        self.file_size - self.header_size - self.tail_size
+        self.file_size
+            .saturating_sub(self.header_size)
+            .saturating_sub(self.tail_size)
+    }
+}
+
+/// Where a reader's volumes came from, so that
+/// [`try_clone`](MultiVolumeReader::try_clone) can open them again.
+#[derive(Clone)]
+enum Origin<'a> {
+    Files(Vec<PathBuf>),
+    Owned(Arc<Vec<u8>>),
+    Borrowed(&'a [u8]),
+    Source,
+}
+
+/// An owned buffer shared between clones of a reader.
+struct SharedBytes(Arc<Vec<u8>>);
+
+impl AsRef<[u8]> for SharedBytes {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+/// The origin and layout of a reader's volumes, from which independent
+/// readers can be opened; see [`MultiVolumeReader::reopener`].
+pub(crate) struct Reopener<'a> {
+    origin: Origin<'a>,
+    /// `(file_size, header_size, tail_size)` of each volume.
+    layout: Vec<(u64, u64, u64)>,
+    tail: [u8; 16],
+}
+
+impl<'a> Reopener<'a> {
+    /// Open the volumes again as a reader positioned at the start.
+    pub(crate) fn open(&self) -> io::Result<MultiVolumeReader<'a>> {
+        let files: Vec<Box<dyn ReadSeek + 'a>> = match &self.origin {
+            Origin::Files(paths) => paths
+                .iter()
+                .map(|path| Ok(Box::new(File::open(path)?) as Box<dyn ReadSeek>))
+                .collect::<io::Result<_>>()?,
+            Origin::Owned(data) => vec![Box::new(io::Cursor::new(SharedBytes(Arc::clone(data))))],
+            Origin::Borrowed(data) => vec![Box::new(io::Cursor::new(*data))],
+            Origin::Source => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "volumes from a VolumeSource cannot be reopened",
+                ));
+            }
+        };
+        let volumes = self
+            .layout
+            .iter()
+            .zip(files)
+            .map(|(&(file_size, header_size, tail_size), file)| Volume {
+                file,
+                file_size,
+                header_size,
+                tail_size,
+            })
+            .collect();
+        let mut reader = MultiVolumeReader {
+            volumes,
+            cur_volume: 0,
+            virtual_pos: 0,
+            tail: self.tail,
+            origin: self.origin.clone(),
+        };
+        reader.seek(SeekFrom::Start(0))?;
+        Ok(reader)
     }
 }
 
 /// Virtual reader over multi-volume ALZ archives (.alz, .a00, .a01, ...).
 /// Transparently handles seeking and reading across volume boundaries.
-pub struct MultiVolumeReader {
-    volumes: Vec<Volume>,
+///
+/// The lifetime is that of borrowed data ([`from_slice`](Self::from_slice));
+/// readers over files or owned buffers are `MultiVolumeReader<'static>`.
+pub struct MultiVolumeReader<'a> {
+    volumes: Vec<Volume<'a>>,
     cur_volume: usize,
     virtual_pos: u64,
     tail: [u8; 16],
+    origin: Origin<'a>,
 }
 
-impl MultiVolumeReader {
+impl<'a> MultiVolumeReader<'a> {
     /// Open a multi-volume archive starting from the given .alz path.
-    /// Discovers .a00, .a01, ... .a99, .b00, ... automatically.
+    /// Discovers .a00, .a01, ... .a99, .b00, ... automatically. A path to
+    /// one of the continuation volumes opens the archive from its .alz.
     pub fn open<P: AsRef<Path>>(path: P) -> AlzResult<Self> {
         let path = path.as_ref();
         let path_str = path.to_string_lossy().to_string();
@@ -46,11 +139,11 @@ impl MultiVolumeReader {
                 "path too short",
             )));
         }
+        let path_str = first_volume_path(&path_str).unwrap_or(path_str);
 
         let prefix = &path_str[..path_str.len() - 3];
-        let mut volumes = Vec::new();
-
-        for i in 0..MAX_VOLUMES {
+        let mut paths = Vec::new();
+        let source = |i: usize| {
             let vol_path = if i == 0 {
                 path_str.clone()
             } else {
@@ -58,18 +151,39 @@ impl MultiVolumeReader {
                 let num = (i - 1) % 100;
                 format!("{prefix}{letter}{num:02}")
             };
+            let file = File::open(&vol_path).ok()?;
+            paths.push(PathBuf::from(vol_path));
+            Some(Box::new(file) as Box<dyn ReadSeek>)
+        };
+        let mut reader = Self::from_source(source, &path_str)?;
+        reader.origin = Origin::Files(paths);
+        Ok(reader)
+    }
+
+    /// Open a multi-volume archive whose volumes come from `source`.
+    ///
+    /// Volumes are requested in order starting at 0 until the source returns
+    /// `None` (or 1000 volumes have been opened).
+    pub fn open_with<S: VolumeSource>(source: S) -> AlzResult<Self> {
+        Self::from_source(source, "volume 0")
+    }
+
+    /// Open volumes from `source`; `name` identifies the first volume in
+    /// the error when there is none.
+    fn from_source<S: VolumeSource>(mut source: S, name: &str) -> AlzResult<Self> {
+        let mut volumes = Vec::new();
 
-            let file = match File::open(&vol_path) {
-                Ok(f) => f,
-                Err(_) => break,
+        for i in 0..MAX_VOLUMES {
+            let Some(mut file) = source.open_volume(i)? else {
+                break;
             };
 
-            let file_size = file.metadata()?.len();
+            let file_size = file.seek(SeekFrom::End(0))?;
             let header_size = if i == 0 { 0 } else { VOLUME_HEADER_SIZE };
             let tail_size = VOLUME_TRAILER_SIZE; // corrected for last volume below
 
             volumes.push(Volume {
-                file: Box::new(file),
+                file,
                 file_size,
                 header_size,
                 tail_size,
@@ -79,7 +193,7 @@ impl MultiVolumeReader {
         if volumes.is_empty() {
             return Err(AlzError::CantOpenFile(io::Error::new(
                 io::ErrorKind::NotFound,
-                format!("can't open: {path_str}"),
+                format!("can't open: {name}"),
             )));
         }
 
@@ -101,6 +215,7 @@ impl MultiVolumeReader {
             cur_volume: 0,
             virtual_pos: 0,
             tail,
+            origin: Origin::Source,
         };
         // Position at the data start of volume 0.
         reader.seek_to_virtual(0)?;
@@ -109,14 +224,29 @@ impl MultiVolumeReader {
 
     /// Create a single-volume reader from in-memory data (e.g. stdin).
     pub fn from_bytes(data: Vec<u8>) -> Self {
-        let len = data.len() as u64;
+        let data = Arc::new(data);
+        Self::single(
+            io::Cursor::new(SharedBytes(Arc::clone(&data))),
+            Origin::Owned(data),
+        )
+    }
+
+    /// Create a single-volume reader over borrowed data, e.g. a
+    /// memory-mapped file, without copying it.
+    pub fn from_slice(data: &'a [u8]) -> Self {
+        Self::single(io::Cursor::new(data), Origin::Borrowed(data))
+    }
+
+    fn single<T: AsRef<[u8]> + 'a>(data: io::Cursor<T>, origin: Origin<'a>) -> Self {
+        let bytes = data.get_ref().as_ref();
+        let len = bytes.len() as u64;
         let mut tail = [0u8; 16];
-        if data.len() >= 16 {
-            tail.copy_from_slice(&data[data.len() - 16..]);
+        if bytes.len() >= 16 {
+            tail.copy_from_slice(&bytes[bytes.len() - 16..]);
         }
         MultiVolumeReader {
             volumes: vec![Volume {
-                file: Box::new(io::Cursor::new(data)),
+                file: Box::new(data),
                 file_size: len,
                 header_size: 0,
                 tail_size: 0,
@@ -124,6 +254,29 @@ impl MultiVolumeReader {
             cur_volume: 0,
             virtual_pos: 0,
             tail,
+            origin,
+        }
+    }
+
+    /// Open the same volumes again as an independent reader, positioned at
+    /// the start. Volume files are reopened by path and in-memory data is
+    /// shared rather than copied. Readers from [`open_with`](Self::open_with)
+    /// cannot be reopened and return [`io::ErrorKind::Unsupported`].
+    pub fn try_clone(&self) -> io::Result<Self> {
+        self.reopener().open()
+    }
+
+    /// What [`try_clone`](Self::try_clone) needs to open the volumes again,
+    /// detached from the open handles so it can be shared between threads.
+    pub(crate) fn reopener(&self) -> Reopener<'a> {
+        Reopener {
+            origin: self.origin.clone(),
+            layout: self
+                .volumes
+                .iter()
+                .map(|vol| (vol.file_size, vol.header_size, vol.tail_size))
+                .collect(),
+            tail: self.tail,
         }
     }
 
@@ -132,9 +285,70 @@ impl MultiVolumeReader {
         &self.tail
     }
 
+    /// Number of volume files.
+    pub fn volume_count(&self) -> usize {
+        self.volumes.len()
+    }
+
+    /// Path of volume `index`, for readers opened from files with
+    /// [`open`](Self::open).
+    pub fn volume_path(&self, index: usize) -> Option<&Path> {
+        match &self.origin {
+            Origin::Files(paths) => paths.get(index).map(PathBuf::as_path),
+            _ => None,
+        }
+    }
+
+    /// Leave the first `len` bytes of the first volume out of the virtual
+    /// data, e.g. the program stub of a self-extracting archive, and move
+    /// back to the (new) start.
+    pub fn skip_prefix(&mut self, len: u64) -> AlzResult<()> {
+        let vol = &mut self.volumes[0];
+        if len > vol.data_size() {
+            return Err(AlzError::CorruptedFile);
+        }
+        vol.header_size += len;
+        self.seek_to_virtual(0)
+    }
+
     /// Total virtual data size across all volumes.
     pub fn total_size(&self) -> u64 {
-        self.volumes.iter().map(|v| v.data_size()).sum()
+        self.volumes
+            .iter()
+            .fold(0u64, |acc, v| acc.saturating_add(v.data_size()))
+    }
+
+    /// Write the archive as a single volume: the data of every volume in
+    /// order, without the continuation headers and volume tails. Returns the
+    /// number of bytes written; the read position is left at the end.
+    pub fn reassemble<W: Write>(&mut self, mut out: W) -> AlzResult<u64> {
+        self.seek_to_virtual(0)?;
+        Ok(io::copy(self, &mut out)?)
+    }
+
+    /// Map a virtual byte range to the physical volume ranges holding it.
+    ///
+    /// Returns `(volume_index, phys_start, phys_len)` for each volume the
+    /// range touches, in order. Parts of the range past the end of the last
+    /// volume are dropped; an empty range maps to no volumes.
+    pub fn locate(&self, virtual_offset: u64, len: u64) -> Vec<(usize, u64, u64)> {
+        let end = virtual_offset.saturating_add(len);
+        let mut spans = Vec::new();
+        let mut vol_start = 0u64;
+
+        for (i, vol) in self.volumes.iter().enumerate() {
+            let vol_end = vol_start.saturating_add(vol.data_size());
+            let start = virtual_offset.max(vol_start);
+            let stop = end.min(vol_end);
+            if start < stop {
+                spans.push((i, vol.header_size + (start - vol_start), stop - start));
+            }
+            if vol_end >= end {
+                break;
+            }
+            vol_start = vol_end;
+        }
+        spans
     }
 
     fn seek_to_virtual(&mut self, offset: u64) -> AlzResult<()> {
@@ -155,14 +369,36 @@ impl MultiVolumeReader {
         // Past end of available volumes -- park at EOF so reads return 0.
         let last = self.volumes.len() - 1;
         let vol = &mut self.volumes[last];
-        let end = vol.file_size - vol.tail_size;
+        let end = vol.file_size.saturating_sub(vol.tail_size);
         vol.file.seek(SeekFrom::Start(end))?;
         self.cur_volume = last;
         Ok(())
     }
 }
 
-impl Read for MultiVolumeReader {
+/// If `path` names a continuation volume (`.a00` ... `.z99`) and its `.alz`
+/// sibling exists and starts with the ALZ signature, return the sibling.
+fn first_volume_path(path: &str) -> Option<String> {
+    let prefix = path.get(..path.len() - 3)?;
+    let ext = path.get(path.len() - 3..)?.as_bytes();
+    if !prefix.ends_with('.')
+        || !ext[0].is_ascii_alphabetic()
+        || !ext[1..].iter().all(u8::is_ascii_digit)
+    {
+        return None;
+    }
+    let alz = if ext[0].is_ascii_uppercase() {
+        "ALZ"
+    } else {
+        "alz"
+    };
+    let first = format!("{prefix}{alz}");
+    let mut magic = [0u8; 4];
+    File::open(&first).ok()?.read_exact(&mut magic).ok()?;
+    (magic == *b"ALZ\x01").then_some(first)
+}
+
+impl Read for MultiVolumeReader<'_> {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
         if buf.is_empty() || self.cur_volume >= self.volumes.len() {
             return Ok(0);
@@ -173,8 +409,9 @@ impl Read for MultiVolumeReader {
         while total_read < buf.len() && self.cur_volume < self.volumes.len() {
             let vol = &mut self.volumes[self.cur_volume];
             let phys_pos = vol.file.stream_position()?;
-            let data_end = vol.file_size - vol.tail_size;
-            let avail = data_end.saturating_sub(phys_pos) as usize;
+            // A crafted volume can be shorter than its trailer.
+            let data_end = vol.file_size.saturating_sub(vol.tail_size);
+            let avail = data_end.saturating_sub(phys_pos);
 
             if avail == 0 {
                 // Move to next volume.
@@ -187,7 +424,8 @@ impl Read for MultiVolumeReader {
                 continue;
             }
 
-            let to_read = avail.min(buf.len() - total_read);
+            // Clamp in u64 first: `avail as usize` truncates on 32-bit targets.
+            let to_read = avail.min((buf.len() - total_read) as u64) as usize;
             let n = vol.file.read(&mut buf[total_read..total_read + to_read])?;
             if n == 0 {
                 break;
@@ -200,26 +438,14 @@ impl Read for MultiVolumeReader {
     }
 }
 
-impl Seek for MultiVolumeReader {
+impl Seek for MultiVolumeReader<'_> {
     fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
         let new_pos = match pos {
-            SeekFrom::Start(p) => p,
-            SeekFrom::Current(delta) => {
-                if delta >= 0 {
-                    self.virtual_pos + delta as u64
-                } else {
-                    self.virtual_pos
-                        .checked_sub((-delta) as u64)
-                        .ok_or_else(|| {
-                            io::Error::new(io::ErrorKind::InvalidInput, "seek before start")
-                        })?
-                }
-            }
-            SeekFrom::End(delta) => {
-                let total = self.total_size() as i64;
-                (total + delta) as u64
-            }
-        };
+            SeekFrom::Start(p) => Some(p),
+            SeekFrom::Current(delta) => self.virtual_pos.checked_add_signed(delta),
+            SeekFrom::End(delta) => self.total_size().checked_add_signed(delta),
+        }
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "seek out of range"))?;
 
         self.seek_to_virtual(new_pos)
             .map_err(|e| io::Error::other(e.to_string()))?;
@@ -0,0 +1,36 @@
+//! Runtime known-answer tests.
+//! Lets users on unusual targets check for miscompiles or endianness bugs.
+
+use crate::{crypto, dostime, encoding};
+
+/// Run all built-in known-answer vectors.
+/// Returns a description of the first failure.
+pub fn run_self_test() -> Result<(), String> {
+    let mut h = crc32fast::Hasher::new();
+    h.update(b"123456789");
+    let crc = h.finalize();
+    if crc != 0xCBF43926 {
+        return Err(format!(
+            "CRC32 check value: expected cbf43926, got {crc:08x}"
+        ));
+    }
+
+    crypto::self_test()?;
+    dostime::self_test()?;
+
+    let name = encoding::cp949_to_utf8(b"\xbf\xee\xbf\xb5");
+    if name != "운영" {
+        return Err(format!("CP949 decode: got {name:?}"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_self_test() {
+        assert_eq!(run_self_test(), Ok(()));
+    }
+}
@@ -0,0 +1,254 @@
+//! Destinations for extracted entries.
+//!
+//! Extraction hands every entry to an [`ExtractSink`] using the entry's
+//! relative path inside the archive (forward slashes, already checked for
+//! `../` traversal). [`RealFsSink`] writes to a directory on disk,
+//! [`PipeSink`] streams file contents to stdout and [`MemorySink`] collects
+//! everything in maps.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use filetime::FileTime;
+
+use crate::error::{AlzError, AlzResult};
+
+/// Filesystem operations used by extraction.
+pub trait ExtractSink {
+    /// Create a directory and any missing parents.
+    fn create_dir(&mut self, path: &Path) -> AlzResult<()>;
+
+    /// Create (or truncate) a file and return a writer for its contents.
+    fn create_file(&mut self, path: &Path) -> AlzResult<Box<dyn Write + '_>>;
+
+    /// Create a symbolic link at `path` pointing to `target`.
+    fn symlink(&mut self, target: &str, path: &Path) -> AlzResult<()>;
+
+    /// Create a file standing in for one of `len` bytes whose contents were
+    /// not extracted. The default creates an empty file.
+    fn create_placeholder(&mut self, path: &Path, _len: u64) -> AlzResult<()> {
+        self.create_file(path)?;
+        Ok(())
+    }
+
+    /// Set the modification time of a previously created file.
+    fn set_mtime(&mut self, _path: &Path, _time: SystemTime) -> AlzResult<()> {
+        Ok(())
+    }
+
+    /// Size and modification time (if known) of a file already at `path`,
+    /// used to resume an interrupted extraction. The default reports none.
+    fn existing(&mut self, _path: &Path) -> Option<(u64, Option<SystemTime>)> {
+        None
+    }
+
+    /// Open a file already at `path` for reading, to check its CRC when
+    /// resuming.
+    fn open_existing(&mut self, _path: &Path) -> Option<Box<dyn Read + '_>> {
+        None
+    }
+
+    /// Remove a previously created file, e.g. after a CRC failure.
+    fn remove_file(&mut self, path: &Path) -> AlzResult<()>;
+
+    /// Rename a previously created file, e.g. to keep a CRC failure.
+    fn rename(&mut self, from: &Path, to: &Path) -> AlzResult<()>;
+}
+
+/// Extract into a directory on the local filesystem.
+pub struct RealFsSink {
+    dest_dir: PathBuf,
+    /// The destination, created and canonicalized on first use.
+    canonical_dest: Option<PathBuf>,
+}
+
+impl RealFsSink {
+    pub fn new<P: Into<PathBuf>>(dest_dir: P) -> Self {
+        RealFsSink {
+            dest_dir: dest_dir.into(),
+            canonical_dest: None,
+        }
+    }
+
+    /// Map an archive path under the destination, creating the path's
+    /// parent directories (and the destination, the first time). Rejects
+    /// absolute paths and anything that escapes the destination (e.g.
+    /// through an existing symlink).
+    fn resolve(&mut self, path: &Path) -> AlzResult<PathBuf> {
+        let dest_path = self.dest_dir.join(path);
+        let canonical_dest = match &self.canonical_dest {
+            Some(dest) => dest,
+            None => {
+                fs::create_dir_all(&self.dest_dir)?;
+                self.canonical_dest
+                    .insert(fs::canonicalize(&self.dest_dir)?)
+            }
+        };
+        // dest_path may not exist yet; resolve via its parent directory.
+        let resolved = if let Some(parent) = dest_path.parent() {
+            fs::create_dir_all(parent)?;
+            fs::canonicalize(parent)?.join(dest_path.file_name().unwrap_or_default())
+        } else {
+            dest_path.clone()
+        };
+        if !resolved.starts_with(canonical_dest) {
+            return Err(AlzError::PathTraversal(path.to_string_lossy().into_owned()));
+        }
+        Ok(dest_path)
+    }
+}
+
+impl ExtractSink for RealFsSink {
+    fn create_dir(&mut self, path: &Path) -> AlzResult<()> {
+        let dest_path = self.resolve(path)?;
+        fs::create_dir_all(dest_path)?;
+        Ok(())
+    }
+
+    fn create_file(&mut self, path: &Path) -> AlzResult<Box<dyn Write + '_>> {
+        let dest_path = self.resolve(path)?;
+        let file = fs::File::create(dest_path).map_err(AlzError::CantOpenDestFile)?;
+        Ok(Box::new(file))
+    }
+
+    /// Create a sparse file of `len` bytes where the filesystem supports it.
+    fn create_placeholder(&mut self, path: &Path, len: u64) -> AlzResult<()> {
+        let dest_path = self.resolve(path)?;
+        let file = fs::File::create(dest_path).map_err(AlzError::CantOpenDestFile)?;
+        file.set_len(len).map_err(AlzError::CantOpenDestFile)?;
+        Ok(())
+    }
+
+    fn symlink(&mut self, target: &str, path: &Path) -> AlzResult<()> {
+        let dest_path = self.resolve(path)?;
+        if target.contains("../") || target.contains("..\\") || Path::new(target).has_root() {
+            return Err(AlzError::PathTraversal(target.to_string()));
+        }
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(target, &dest_path)?;
+        #[cfg(not(unix))]
+        fs::write(&dest_path, target.as_bytes())?;
+        Ok(())
+    }
+
+    fn set_mtime(&mut self, path: &Path, time: SystemTime) -> AlzResult<()> {
+        let ft = FileTime::from_system_time(time);
+        filetime::set_file_mtime(self.dest_dir.join(path), ft)?;
+        Ok(())
+    }
+
+    fn existing(&mut self, path: &Path) -> Option<(u64, Option<SystemTime>)> {
+        let meta = fs::symlink_metadata(self.dest_dir.join(path)).ok()?;
+        meta.is_file().then(|| (meta.len(), meta.modified().ok()))
+    }
+
+    fn open_existing(&mut self, path: &Path) -> Option<Box<dyn Read + '_>> {
+        let file = fs::File::open(self.dest_dir.join(path)).ok()?;
+        Some(Box::new(file))
+    }
+
+    fn remove_file(&mut self, path: &Path) -> AlzResult<()> {
+        fs::remove_file(self.dest_dir.join(path))?;
+        Ok(())
+    }
+
+    fn rename(&mut self, from: &Path, to: &Path) -> AlzResult<()> {
+        fs::rename(self.dest_dir.join(from), self.dest_dir.join(to))?;
+        Ok(())
+    }
+}
+
+/// Write file contents (and symlink targets) to stdout; skip directories.
+pub struct PipeSink;
+
+impl ExtractSink for PipeSink {
+    fn create_dir(&mut self, _path: &Path) -> AlzResult<()> {
+        Ok(())
+    }
+
+    fn create_file(&mut self, _path: &Path) -> AlzResult<Box<dyn Write + '_>> {
+        Ok(Box::new(io::stdout().lock()))
+    }
+
+    fn create_placeholder(&mut self, _path: &Path, _len: u64) -> AlzResult<()> {
+        Ok(())
+    }
+
+    fn symlink(&mut self, target: &str, _path: &Path) -> AlzResult<()> {
+        io::stdout()
+            .lock()
+            .write_all(target.as_bytes())
+            .map_err(AlzError::CantOpenDestFile)
+    }
+
+    fn remove_file(&mut self, _path: &Path) -> AlzResult<()> {
+        Ok(())
+    }
+
+    fn rename(&mut self, _from: &Path, _to: &Path) -> AlzResult<()> {
+        Ok(())
+    }
+}
+
+/// Collect extracted entries in memory, keyed by archive path.
+#[derive(Debug, Default)]
+pub struct MemorySink {
+    pub files: BTreeMap<PathBuf, Vec<u8>>,
+    pub dirs: BTreeSet<PathBuf>,
+    pub symlinks: BTreeMap<PathBuf, String>,
+    /// Placeholder files and their sizes.
+    pub placeholders: BTreeMap<PathBuf, u64>,
+}
+
+impl MemorySink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ExtractSink for MemorySink {
+    fn create_dir(&mut self, path: &Path) -> AlzResult<()> {
+        self.dirs.insert(path.to_path_buf());
+        Ok(())
+    }
+
+    fn create_file(&mut self, path: &Path) -> AlzResult<Box<dyn Write + '_>> {
+        let buf = self.files.entry(path.to_path_buf()).or_default();
+        buf.clear();
+        Ok(Box::new(buf))
+    }
+
+    fn create_placeholder(&mut self, path: &Path, len: u64) -> AlzResult<()> {
+        self.placeholders.insert(path.to_path_buf(), len);
+        Ok(())
+    }
+
+    fn symlink(&mut self, target: &str, path: &Path) -> AlzResult<()> {
+        self.symlinks.insert(path.to_path_buf(), target.to_string());
+        Ok(())
+    }
+
+    fn existing(&mut self, path: &Path) -> Option<(u64, Option<SystemTime>)> {
+        self.files.get(path).map(|data| (data.len() as u64, None))
+    }
+
+    fn open_existing(&mut self, path: &Path) -> Option<Box<dyn Read + '_>> {
+        let data = self.files.get(path)?;
+        Some(Box::new(data.as_slice()))
+    }
+
+    fn remove_file(&mut self, path: &Path) -> AlzResult<()> {
+        self.files.remove(path);
+        Ok(())
+    }
+
+    fn rename(&mut self, from: &Path, to: &Path) -> AlzResult<()> {
+        if let Some(data) = self.files.remove(from) {
+            self.files.insert(to.to_path_buf(), data);
+        }
+        Ok(())
+    }
+}
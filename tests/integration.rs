@@ -1,8 +1,14 @@
 use std::io::Cursor;
+use std::path::Path;
 use std::sync::atomic::{AtomicU32, Ordering};
 
 use unalz_rs::archive::{AlzArchive, CompressionMethod};
 use unalz_rs::decompress::{bzip2, deflate, raw};
+use unalz_rs::error::AlzError;
+use unalz_rs::extract::ExtractOptions;
+use unalz_rs::format::ArchiveFormat;
+use unalz_rs::multivolume::{MultiVolumeReader, ReadSeek};
+use unalz_rs::sink::MemorySink;
 
 /// Minimal ALZ archive: one file "t/t.txt" containing "42", DEFLATE compressed.
 /// From patool test suite (https://github.com/wummel/patool).
@@ -13,6 +19,69 @@ const T_ALZ: &[u8] = &[
     0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x43, 0x4c, 0x5a, 0x02,
 ];
 
+/// Build a local file header record (with 4-byte size fields) and its data.
+fn local_file(
+    name: &[u8],
+    attr: u8,
+    method: u8,
+    crc: u32,
+    uncompressed_size: u32,
+    data: &[u8],
+) -> Vec<u8> {
+    let mut rec = b"BLZ\x01".to_vec();
+    rec.extend_from_slice(&(name.len() as u16).to_le_bytes());
+    rec.push(attr);
+    rec.extend_from_slice(&0x4E8C2209u32.to_le_bytes()); // 2019-04-12 04:16:18
+    rec.push(0x40); // 4-byte size fields
+    rec.push(0);
+    rec.push(method);
+    rec.push(0);
+    rec.extend_from_slice(&crc.to_le_bytes());
+    rec.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    rec.extend_from_slice(&uncompressed_size.to_le_bytes());
+    rec.extend_from_slice(name);
+    rec.extend_from_slice(data);
+    rec
+}
+
+/// A Store record for `data` with a correct CRC.
+fn store_file(name: &str, data: &[u8]) -> Vec<u8> {
+    local_file(
+        name.as_bytes(),
+        0x20,
+        0,
+        crc32(data),
+        data.len() as u32,
+        data,
+    )
+}
+
+/// Wrap records in an ALZ file header and end-of-central-directory marker.
+fn build_alz(records: &[Vec<u8>]) -> Vec<u8> {
+    let mut out = vec![0x41, 0x4c, 0x5a, 0x01, 0x0a, 0x00, 0x00, 0x00];
+    for rec in records {
+        out.extend_from_slice(rec);
+    }
+    out.extend_from_slice(b"CLZ\x02");
+    out
+}
+
+fn deflate(data: &[u8]) -> Vec<u8> {
+    use flate2::Compression;
+    use flate2::write::DeflateEncoder;
+    use std::io::Write;
+
+    let mut enc = DeflateEncoder::new(Vec::new(), Compression::default());
+    enc.write_all(data).unwrap();
+    enc.finish().unwrap()
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut h = crc32fast::Hasher::new();
+    h.update(data);
+    h.finalize()
+}
+
 static COUNTER: AtomicU32 = AtomicU32::new(0);
 
 /// Per-test unique directory to avoid conflicts with parallel test execution.
@@ -24,7 +93,15 @@ fn test_dir() -> std::path::PathBuf {
     dir
 }
 
-fn open_test_archive() -> (AlzArchive, std::path::PathBuf) {
+/// Default options without the per-entry progress messages.
+fn quiet() -> ExtractOptions<'static> {
+    ExtractOptions {
+        quiet: true,
+        ..Default::default()
+    }
+}
+
+fn open_test_archive() -> (AlzArchive<'static>, std::path::PathBuf) {
     let dir = test_dir();
     let path = dir.join("test.alz");
     std::fs::write(&path, T_ALZ).unwrap();
@@ -42,10 +119,212 @@ fn parse_archive() {
     assert_eq!(entry.uncompressed_size, 2);
     assert_eq!(entry.compressed_size, 4);
     assert_eq!(entry.compression_method, CompressionMethod::Deflate);
+    assert_eq!(entry.compression_method.as_byte(), 2);
+    assert_eq!(entry.raw_reserved, [0x00, 0x00]);
     assert!(!entry.is_encrypted());
     assert!(!entry.is_directory());
 }
 
+#[test]
+fn other_archive_formats_recognized() {
+    let not_alz = |data: &[u8]| AlzArchive::from_bytes(data.to_vec()).err().unwrap();
+    assert!(matches!(
+        not_alz(b"PK\x03\x04\x14\x00\x00\x00"),
+        AlzError::IsZipNotAlz
+    ));
+    assert!(matches!(
+        not_alz(b"EGGA\x00\x01\x00\x00"),
+        AlzError::UnsupportedFormat(ArchiveFormat::Egg)
+    ));
+    assert!(matches!(
+        not_alz(b"Rar!\x1a\x07\x00"),
+        AlzError::UnsupportedFormat(ArchiveFormat::Rar)
+    ));
+    assert!(matches!(not_alz(b"hello world"), AlzError::NotAlzFile));
+}
+
+#[test]
+fn normalized_name_and_components() {
+    let archive = AlzArchive::from_bytes(build_alz(&[store_file("a\\b//c\\d.txt", b"x")])).unwrap();
+    let entry = &archive.entries[0];
+    assert_eq!(entry.normalized_name(), "a/b//c/d.txt");
+    assert_eq!(
+        entry.components().collect::<Vec<_>>(),
+        ["a", "b", "c", "d.txt"]
+    );
+}
+
+#[cfg(feature = "normalize")]
+#[test]
+fn names_normalized_to_nfc() {
+    use unalz_rs::archive::ParseOptions;
+    use unalz_rs::encoding::{NameDecoding, NameNormalization};
+
+    let nfc = "\u{D55C}.txt"; // 한
+    let nfd = "\u{1112}\u{1161}\u{11AB}.txt";
+    let alz = build_alz(&[local_file(nfd.as_bytes(), 0x20, 0, crc32(b"x"), 1, b"x")]);
+    let opts = ParseOptions {
+        name_decoding: NameDecoding::from_label("utf-8").unwrap(),
+        normalization: NameNormalization::Nfc,
+        ..Default::default()
+    };
+    let reader = MultiVolumeReader::from_bytes(alz.clone());
+    let archive = AlzArchive::from_reader_with(reader, &opts).unwrap();
+    assert_eq!(archive.entries[0].file_name, nfc);
+    assert!(archive.contains(nfc) && archive.contains(nfd));
+
+    let opts = ParseOptions {
+        normalization: NameNormalization::None,
+        ..opts
+    };
+    let reader = MultiVolumeReader::from_bytes(alz);
+    let archive = AlzArchive::from_reader_with(reader, &opts).unwrap();
+    assert_eq!(archive.entries[0].file_name, nfd);
+    assert!(!archive.contains(nfc));
+}
+
+#[test]
+fn name_decoding_option() {
+    use unalz_rs::archive::ParseOptions;
+    use unalz_rs::encoding::NameDecoding;
+
+    let mut rec = store_file("xx.txt", b"x");
+    rec[27..29].copy_from_slice(b"\xc3\xa9");
+    let alz = build_alz(&[rec]);
+    let archive = AlzArchive::from_bytes(alz.clone()).unwrap();
+    assert_eq!(archive.entries[0].file_name, "챕.txt");
+
+    let opts = ParseOptions {
+        name_decoding: NameDecoding::Utf8First,
+        ..Default::default()
+    };
+    let reader = MultiVolumeReader::from_bytes(alz);
+    let archive = AlzArchive::from_reader_with(reader, &opts).unwrap();
+    assert_eq!(archive.entries[0].file_name, "é.txt");
+}
+
+#[test]
+fn reserved_header_bytes_kept() {
+    let mut rec = store_file("r.txt", b"r");
+    rec[12] = 0xAB; // after the descriptor
+    rec[14] = 0xCD; // after the method
+    let archive = AlzArchive::from_bytes(build_alz(&[rec])).unwrap();
+    assert_eq!(archive.entries[0].raw_reserved, [0xAB, 0xCD]);
+}
+
+#[test]
+fn compression_method_byte_round_trip() {
+    for m in [
+        CompressionMethod::Store,
+        CompressionMethod::Bzip2,
+        CompressionMethod::Deflate,
+        CompressionMethod::Unknown(3),
+        CompressionMethod::Unknown(255),
+    ] {
+        assert_eq!(CompressionMethod::from_byte(m.as_byte()), m);
+    }
+    for b in 0..=255u8 {
+        assert_eq!(CompressionMethod::from_byte(b).as_byte(), b);
+    }
+    assert_eq!(format!("{:<7}|", CompressionMethod::Store), "Store  |");
+    assert_eq!(
+        format!("{:<7}|", CompressionMethod::Unknown(5)),
+        "Unknown(5)|"
+    );
+}
+
+#[test]
+fn archive_totals() {
+    let (archive, _dir) = open_test_archive();
+    assert_eq!(archive.total_uncompressed_size(), 2);
+    assert_eq!(archive.total_compressed_size(), 4);
+    assert_eq!(archive.file_count(), 1);
+
+    let alz = build_alz(&[
+        local_file(b"dir/", 0x10, 0, 0, 0, b""),
+        store_file("dir/a", b"abc"),
+        store_file("b", b"de"),
+    ]);
+    let mut archive = AlzArchive::from_bytes(alz).unwrap();
+    assert_eq!(archive.total_uncompressed_size(), 5);
+    assert_eq!(archive.file_count(), 2);
+    assert_eq!(archive.extraction_size(None, &quiet()), 5);
+    let names = ["b".to_string(), "missing".to_string(), "dir/".to_string()];
+    assert_eq!(archive.extraction_size(Some(&names), &quiet()), 2);
+
+    // Sizes that overflow when summed saturate instead of panicking.
+    archive.entries[1].uncompressed_size = u64::MAX;
+    archive.entries[1].compressed_size = u64::MAX - 1;
+    assert_eq!(archive.total_uncompressed_size(), u64::MAX);
+    assert_eq!(archive.total_compressed_size(), u64::MAX);
+}
+
+#[test]
+fn extraction_size_follows_selection_options() {
+    let alz = build_alz(&[
+        store_file("dup.txt", b"first"),
+        store_file("big.bin", b"0123456789"),
+        store_file("a.txt", b"a"),
+        store_file("dup.txt", b"second"),
+    ]);
+    let archive = AlzArchive::from_bytes(alz).unwrap();
+    let size =
+        |files: Option<&[String]>, opts: ExtractOptions| archive.extraction_size(files, &opts);
+    assert_eq!(size(None, quiet()), 22);
+    let latest = ExtractOptions {
+        latest_only: true,
+        ..quiet()
+    };
+    assert_eq!(size(None, latest), 17);
+    let names = ["dup.txt".to_string()];
+    assert_eq!(size(Some(&names), quiet()), 5);
+    assert_eq!(size(Some(&names), latest), 6);
+    let small = ExtractOptions {
+        max_size: Some(6),
+        min_size: Some(2),
+        ..quiet()
+    };
+    assert_eq!(size(None, small), 11);
+    // The limit counts files left after the size filter.
+    let limited = ExtractOptions {
+        limit: Some(1),
+        min_size: Some(2),
+        ..quiet()
+    };
+    assert_eq!(size(None, limited), 5);
+    let limited = ExtractOptions {
+        limit: Some(2),
+        ..limited
+    };
+    assert_eq!(size(None, limited), 15);
+}
+
+#[test]
+fn streaming_list() {
+    let dir = test_dir();
+    let path = dir.join("test.alz");
+    std::fs::write(&path, T_ALZ).unwrap();
+
+    let entries: Vec<_> = AlzArchive::open_streaming(path.to_str().unwrap())
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].file_name, "t/t.txt");
+    assert_eq!(entries[0].data_pos, 38);
+}
+
+#[test]
+fn streaming_list_stops_on_error() {
+    let dir = test_dir();
+    let path = dir.join("bad.alz");
+    std::fs::write(&path, b"not an alz file").unwrap();
+
+    let mut stream = AlzArchive::open_streaming(path.to_str().unwrap()).unwrap();
+    assert!(stream.next().unwrap().is_err());
+    assert!(stream.next().is_none());
+}
+
 #[test]
 fn extract_to_dir() {
     let (mut archive, dir) = open_test_archive();
@@ -56,6 +335,1160 @@ fn extract_to_dir() {
     assert_eq!(std::fs::read(out.join("t/t.txt")).unwrap(), b"42");
 }
 
+#[test]
+fn extract_repeatedly() {
+    let (mut archive, dir) = open_test_archive();
+    for i in 0..3 {
+        let out = dir.join(format!("out{i}"));
+        std::fs::create_dir_all(&out).unwrap();
+        unalz_rs::extract::extract_all(&mut archive, &out, None, false, true).unwrap();
+        assert_eq!(std::fs::read(out.join("t/t.txt")).unwrap(), b"42");
+    }
+}
+
+#[test]
+fn extract_with_split_borrow() {
+    let (mut archive, dir) = open_test_archive();
+    let out = dir.join("out");
+    std::fs::create_dir_all(&out).unwrap();
+
+    let (reader, entries) = archive.reader_and_entries();
+    for entry in entries {
+        unalz_rs::extract::extract_entry_from(reader, entry, &out, None, false).unwrap();
+    }
+    assert_eq!(std::fs::read(out.join("t/t.txt")).unwrap(), b"42");
+}
+
+#[test]
+fn parse_borrowed_slice() {
+    let alz = build_alz(&[store_file("a.txt", b"hello")]);
+    let mut archive = AlzArchive::from_slice(&alz).unwrap();
+    assert_eq!(archive.entries[0].file_name, "a.txt");
+    let mut sink = MemorySink::new();
+    unalz_rs::extract::extract_all_to(&mut archive, &mut sink, &quiet()).unwrap();
+    assert_eq!(sink.files[Path::new("a.txt")], b"hello");
+}
+
+#[test]
+fn extract_custom_entry_subset() {
+    let data = vec![b'z'; 3000];
+    let alz = build_alz(&[
+        store_file("small.txt", b"hi"),
+        local_file(
+            b"big.txt",
+            0x20,
+            2,
+            crc32(&data),
+            data.len() as u32,
+            &deflate(&data),
+        ),
+        store_file("other.txt", b"hello"),
+    ]);
+    let mut archive = AlzArchive::from_bytes(alz).unwrap();
+    let (reader, entries) = archive.reader_and_entries();
+    let selected: Vec<_> = entries
+        .iter()
+        .filter(|e| {
+            e.compression_method == CompressionMethod::Deflate && e.uncompressed_size > 1000
+        })
+        .collect();
+    let mut sink = MemorySink::new();
+    let summary =
+        unalz_rs::extract::extract_entries_to(reader, &selected, &mut sink, &quiet()).unwrap();
+    assert_eq!(summary.entries, 1);
+    assert_eq!(sink.files.len(), 1);
+    assert_eq!(sink.files[Path::new("big.txt")], data);
+}
+
+#[cfg(feature = "sha256")]
+#[test]
+fn sha256_of_extracted_files() {
+    let alz = build_alz(&[store_file("a.txt", b"hello")]);
+    let mut archive = AlzArchive::from_bytes(alz).unwrap();
+    let (reader, entries) = archive.reader_and_entries();
+    let mut sink = MemorySink::new();
+
+    let outcome =
+        unalz_rs::extract::extract_entry_to(reader, &entries[0], &mut sink, &quiet()).unwrap();
+    assert_eq!(outcome.sha256, None);
+
+    let opts = ExtractOptions {
+        sha256: true,
+        ..quiet()
+    };
+    let outcome =
+        unalz_rs::extract::extract_entry_to(reader, &entries[0], &mut sink, &opts).unwrap();
+    let hex: String = outcome
+        .sha256
+        .unwrap()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect();
+    assert_eq!(
+        hex,
+        "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+    );
+    assert_eq!(outcome.crc, crc32(b"hello"));
+}
+
+#[test]
+fn extract_to_memory_sink() {
+    let data: Vec<u8> = (0..5000u32).map(|i| (i % 7) as u8).collect();
+    let alz = build_alz(&[
+        local_file(b"dir/", 0x10, 0, 0, 0, b""),
+        store_file("dir/a.bin", &data),
+        store_file("b.txt", b"hello"),
+    ]);
+    let mut archive = AlzArchive::from_bytes(alz).unwrap();
+    let mut sink = MemorySink::new();
+    let summary = unalz_rs::extract::extract_all_to(&mut archive, &mut sink, &quiet()).unwrap();
+    assert_eq!(summary.entries, 3);
+    assert_eq!(summary.bytes, 5005);
+
+    assert!(sink.dirs.contains(Path::new("dir/")));
+    assert_eq!(sink.files.len(), 2);
+    assert_eq!(sink.files[Path::new("dir/a.bin")], data);
+    assert_eq!(sink.files[Path::new("b.txt")], b"hello");
+}
+
+#[test]
+fn content_filter_keeps_matching_files() {
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+    png.extend((0..5000u32).map(|i| (i % 13) as u8));
+    let text = vec![b'x'; 5000];
+    let alz = build_alz(&[
+        local_file(b"img/", 0x10, 0, 0, 0, b""),
+        local_file(
+            b"img/a.png",
+            0x20,
+            2,
+            crc32(&png),
+            png.len() as u32,
+            &deflate(&png),
+        ),
+        local_file(
+            b"notes.txt",
+            0x20,
+            2,
+            crc32(&text),
+            text.len() as u32,
+            &deflate(&text),
+        ),
+        store_file("tiny.gif", b"GIF89a"),
+        store_file("short.txt", b"hi"),
+    ]);
+    let mut archive = AlzArchive::from_bytes(alz).unwrap();
+    let opts = ExtractOptions {
+        content_filter: Some(&unalz_rs::extract::is_image),
+        ..quiet()
+    };
+    let mut sink = MemorySink::new();
+    let summary = unalz_rs::extract::extract_all_to(&mut archive, &mut sink, &opts).unwrap();
+
+    assert_eq!(summary.entries, 2);
+    assert_eq!(summary.bytes, png.len() as u64 + 6);
+    assert!(sink.dirs.is_empty());
+    assert_eq!(sink.files.len(), 2);
+    assert_eq!(sink.files[Path::new("img/a.png")], png);
+    assert_eq!(sink.files[Path::new("tiny.gif")], b"GIF89a");
+}
+
+#[test]
+fn directory_mtime_applied_after_files() {
+    let dir = test_dir();
+    let alz = build_alz(&[
+        local_file(b"sub/", 0x10, 0, 0, 0, b""),
+        store_file("sub/a.txt", b"hello"),
+        store_file("sub/b.txt", b"world"),
+    ]);
+    let mut archive = AlzArchive::from_bytes(alz).unwrap();
+    let mut sink = unalz_rs::sink::RealFsSink::new(&dir);
+    unalz_rs::extract::extract_all_to(&mut archive, &mut sink, &quiet()).unwrap();
+
+    let expected =
+        unalz_rs::dostime::dos_datetime_to_systime(archive.entries[0].file_time_date).unwrap();
+    let mtime = std::fs::metadata(dir.join("sub"))
+        .unwrap()
+        .modified()
+        .unwrap();
+    assert_eq!(mtime, expected);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn extract_glob_isolates_failures() {
+    let dir = test_dir();
+    let src = dir.join("in");
+    std::fs::create_dir_all(&src).unwrap();
+    std::fs::write(src.join("one.alz"), build_alz(&[store_file("a.txt", b"1")])).unwrap();
+    std::fs::write(
+        src.join("two.alz"),
+        build_alz(&[store_file("b.txt", b"22")]),
+    )
+    .unwrap();
+    std::fs::write(src.join("bad.alz"), b"not an archive").unwrap();
+    std::fs::write(src.join("other.txt"), b"ignored").unwrap();
+
+    let out = dir.join("out");
+    let pattern = format!("{}/*.alz", src.display());
+    let results = unalz_rs::extract::extract_glob(&pattern, &out, &quiet()).unwrap();
+    assert_eq!(results.len(), 3);
+    for r in &results {
+        let stem = r.path.file_stem().unwrap().to_str().unwrap();
+        match stem {
+            "bad" => assert!(matches!(r.result, Err(AlzError::NotAlzFile))),
+            _ => assert_eq!(r.result.as_ref().unwrap().entries, 1),
+        }
+    }
+    assert_eq!(std::fs::read(out.join("one/a.txt")).unwrap(), b"1");
+    assert_eq!(std::fs::read(out.join("two/b.txt")).unwrap(), b"22");
+
+    let err = unalz_rs::extract::extract_glob("[", &out, &quiet()).unwrap_err();
+    assert!(matches!(err, AlzError::InvalidPattern(_)));
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn resume_skips_complete_files() {
+    let dir = test_dir();
+    let alz = build_alz(&[store_file("a.txt", b"hello"), store_file("b.txt", b"world")]);
+    let mut archive = AlzArchive::from_bytes(alz).unwrap();
+    let mut sink = unalz_rs::sink::RealFsSink::new(&dir);
+    unalz_rs::extract::extract_all_to(&mut archive, &mut sink, &quiet()).unwrap();
+
+    // Same size and time but different contents; b.txt went missing.
+    let mtime = std::fs::metadata(dir.join("a.txt"))
+        .unwrap()
+        .modified()
+        .unwrap();
+    std::fs::write(dir.join("a.txt"), b"HELLO").unwrap();
+    filetime::set_file_mtime(
+        dir.join("a.txt"),
+        filetime::FileTime::from_system_time(mtime),
+    )
+    .unwrap();
+    std::fs::remove_file(dir.join("b.txt")).unwrap();
+
+    let opts = ExtractOptions {
+        resume: true,
+        ..quiet()
+    };
+    let summary = unalz_rs::extract::extract_all_to(&mut archive, &mut sink, &opts).unwrap();
+    assert_eq!(summary.entries, 1);
+    assert_eq!(std::fs::read(dir.join("a.txt")).unwrap(), b"HELLO");
+    assert_eq!(std::fs::read(dir.join("b.txt")).unwrap(), b"world");
+
+    let opts = ExtractOptions {
+        resume: true,
+        resume_verify: true,
+        ..quiet()
+    };
+    let summary = unalz_rs::extract::extract_all_to(&mut archive, &mut sink, &opts).unwrap();
+    assert_eq!(summary.entries, 1);
+    assert_eq!(std::fs::read(dir.join("a.txt")).unwrap(), b"hello");
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn verify_extracted_files() {
+    let (mut archive, dir) = open_test_archive();
+    let out = dir.join("out");
+    unalz_rs::extract::extract_all(&mut archive, &out, None, false, true).unwrap();
+    let entry = &archive.entries[0];
+    assert!(entry.verify_file(out.join("t/t.txt")).unwrap());
+    assert!(entry.verify_data(&b"42"[..]).unwrap());
+    assert!(!entry.verify_data(&b"24"[..]).unwrap());
+    assert!(!entry.verify_data(&b"420"[..]).unwrap());
+
+    std::fs::write(out.join("t/t.txt"), b"43").unwrap();
+    assert!(!entry.verify_file(out.join("t/t.txt")).unwrap());
+    assert!(entry.verify_file(out.join("missing")).is_err());
+}
+
+#[test]
+fn extract_into_missing_dest_dir() {
+    let dir = test_dir();
+    let dest = dir.join("new/nested/out");
+    let mut archive = AlzArchive::from_bytes(build_alz(&[store_file("a.txt", b"hello")])).unwrap();
+    let mut sink = unalz_rs::sink::RealFsSink::new(&dest);
+    unalz_rs::extract::extract_all_to(&mut archive, &mut sink, &quiet()).unwrap();
+    assert_eq!(std::fs::read(dest.join("a.txt")).unwrap(), b"hello");
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn placeholders_keep_sizes_without_decompressing() {
+    let text = vec![b'x'; 5000];
+    // Wrong CRC: would fail a real extraction.
+    let alz = build_alz(&[
+        local_file(b"docs/", 0x10, 0, 0, 0, b""),
+        local_file(
+            b"docs/big.txt",
+            0x20,
+            2,
+            crc32(&text) ^ 1,
+            text.len() as u32,
+            &deflate(&text),
+        ),
+        store_file("short.txt", b"hi"),
+    ]);
+    let opts = ExtractOptions {
+        placeholders: true,
+        ..quiet()
+    };
+
+    let mut archive = AlzArchive::from_bytes(alz.clone()).unwrap();
+    let mut sink = MemorySink::new();
+    let summary = unalz_rs::extract::extract_all_to(&mut archive, &mut sink, &opts).unwrap();
+    assert_eq!(summary.bytes, 5002);
+    assert!(sink.files.is_empty());
+    assert!(sink.dirs.contains(Path::new("docs")));
+    assert_eq!(sink.placeholders[Path::new("docs/big.txt")], 5000);
+    assert_eq!(sink.placeholders[Path::new("short.txt")], 2);
+
+    let dir = test_dir();
+    let mut archive = AlzArchive::from_bytes(alz).unwrap();
+    let mut sink = unalz_rs::sink::RealFsSink::new(&dir);
+    unalz_rs::extract::extract_all_to(&mut archive, &mut sink, &opts).unwrap();
+    let meta = std::fs::metadata(dir.join("docs/big.txt")).unwrap();
+    assert_eq!(meta.len(), 5000);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn memory_sink_drops_crc_failures() {
+    let alz = build_alz(&[local_file(b"bad.txt", 0x20, 0, 0x1234, 5, b"hello")]);
+    let mut archive = AlzArchive::from_bytes(alz).unwrap();
+    let mut sink = MemorySink::new();
+    let err = unalz_rs::extract::extract_all_to(&mut archive, &mut sink, &quiet()).unwrap_err();
+    assert!(matches!(err, AlzError::InvalidFileCrc { .. }));
+    assert!(sink.files.is_empty());
+}
+
+#[test]
+fn entry_outcome_and_running_crc() {
+    use std::sync::Mutex;
+    use unalz_rs::extract::EntryOutcome;
+
+    let data: Vec<u8> = (0..100_000u32).map(|i| (i % 97) as u8).collect();
+    let mut archive = AlzArchive::from_bytes(build_alz(&[store_file("a.bin", &data)])).unwrap();
+    let seen = Mutex::new(Vec::new());
+    let progress = |_: &_, written: u64, crc: u32| seen.lock().unwrap().push((written, crc));
+    let opts = ExtractOptions {
+        progress: Some(&progress),
+        ..quiet()
+    };
+    let (reader, entries) = archive.reader_and_entries();
+    let mut sink = MemorySink::new();
+    let outcome =
+        unalz_rs::extract::extract_entry_to(reader, &entries[0], &mut sink, &opts).unwrap();
+    assert_eq!(
+        outcome,
+        EntryOutcome {
+            crc: crc32(&data),
+            size: data.len() as u64,
+            skipped: false,
+            sha256: None,
+            mtime_failed: false,
+        }
+    );
+
+    let seen = seen.into_inner().unwrap();
+    assert!(seen.len() > 1);
+    assert!(seen.windows(2).all(|w| w[0].0 < w[1].0));
+    let (written, crc) = seen[0];
+    assert_eq!(crc, crc32(&data[..written as usize]));
+    assert_eq!(*seen.last().unwrap(), (data.len() as u64, crc32(&data)));
+}
+
+#[test]
+fn cancel_mid_entry_removes_partial_file() {
+    use std::sync::atomic::AtomicBool;
+
+    let data = vec![7u8; 200_000];
+    let alz = build_alz(&[store_file("big.bin", &data), store_file("next.txt", b"n")]);
+    let mut archive = AlzArchive::from_bytes(alz).unwrap();
+    let cancel = AtomicBool::new(false);
+    let progress = |_: &_, written: u64, _| {
+        if written >= 50_000 {
+            cancel.store(true, Ordering::Relaxed);
+        }
+    };
+    let opts = ExtractOptions {
+        cancel: Some(&cancel),
+        progress: Some(&progress),
+        keep_going: true,
+        buf_size: 10_000,
+        ..quiet()
+    };
+    let mut sink = MemorySink::new();
+    let err = unalz_rs::extract::extract_all_to(&mut archive, &mut sink, &opts).unwrap_err();
+    assert!(matches!(err, AlzError::Cancelled));
+    assert_eq!(err.to_string(), "extraction cancelled");
+    assert!(sink.files.is_empty());
+}
+
+#[test]
+fn small_buffer_size() {
+    let data: Vec<u8> = (0..10_000u32).map(|i| (i % 13) as u8).collect();
+    let compressed = deflate(&data);
+    let alz = build_alz(&[
+        store_file("s.bin", &data),
+        local_file(
+            b"d.bin",
+            0x20,
+            2,
+            crc32(&data),
+            data.len() as u32,
+            &compressed,
+        ),
+    ]);
+    let mut archive = AlzArchive::from_bytes(alz).unwrap();
+    let mut sink = MemorySink::new();
+    let opts = ExtractOptions {
+        buf_size: 7,
+        ..quiet()
+    };
+    unalz_rs::extract::extract_all_to(&mut archive, &mut sink, &opts).unwrap();
+    assert_eq!(sink.files[Path::new("s.bin")], data);
+    assert_eq!(sink.files[Path::new("d.bin")], data);
+}
+
+/// Reader without `Seek`, like a pipe.
+struct ForwardOnly<'a>(&'a [u8]);
+
+impl std::io::Read for ForwardOnly<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        // Short reads, to exercise record boundaries.
+        let n = buf.len().min(self.0.len()).min(7);
+        buf[..n].copy_from_slice(&self.0[..n]);
+        self.0 = &self.0[n..];
+        Ok(n)
+    }
+}
+
+#[test]
+fn extract_streaming_forward_only() {
+    let data: Vec<u8> = (0..20_000u32).map(|i| (i % 31) as u8).collect();
+    let compressed = deflate(&data);
+    let mut comment = b"ELZ\x01".to_vec();
+    comment.extend_from_slice(&0xFFFF_FFFFu32.to_le_bytes());
+    comment.extend_from_slice(&5u16.to_le_bytes());
+    comment.extend_from_slice(b"hello");
+    let alz = build_alz(&[
+        local_file(b"d/", 0x10, 0, 0, 0, b""),
+        local_file(
+            b"d/big.bin",
+            0x20,
+            2,
+            crc32(&data),
+            data.len() as u32,
+            &compressed,
+        ),
+        local_file(b"bad.txt", 0x20, 0, 0x1234, 3, b"bad"),
+        comment,
+        store_file("last.txt", b"end"),
+    ]);
+
+    let mut sink = MemorySink::new();
+    let opts = ExtractOptions {
+        keep_going: true,
+        ..quiet()
+    };
+    let err = AlzArchive::extract_streaming(ForwardOnly(&alz), &mut sink, &opts).unwrap_err();
+    assert!(matches!(err, AlzError::InvalidFileCrc { .. }));
+    assert!(sink.dirs.contains(Path::new("d/")));
+    assert_eq!(sink.files[Path::new("d/big.bin")], data);
+    assert_eq!(sink.files[Path::new("last.txt")], b"end");
+    assert!(!sink.files.contains_key(Path::new("bad.txt")));
+
+    // Without keep_going the first failure stops the pass.
+    let mut sink = MemorySink::new();
+    assert!(AlzArchive::extract_streaming(ForwardOnly(&alz), &mut sink, &quiet()).is_err());
+    assert!(!sink.files.contains_key(Path::new("last.txt")));
+}
+
+#[test]
+fn tail_info_fields() {
+    let mut alz = build_alz(&[store_file("a.txt", b"hello")]);
+    for word in [1u32, 4, 0xDEAD_BEEF, 7] {
+        alz.extend_from_slice(&word.to_le_bytes());
+    }
+    let archive = AlzArchive::from_bytes(alz).unwrap();
+    let tail = archive.tail_info();
+    assert_eq!(
+        tail,
+        unalz_rs::archive::TailInfo {
+            end_info0: 1,
+            comment_section_size: 4,
+            end_info2: 0xDEAD_BEEF,
+            end_info3: 7,
+        }
+    );
+    assert!(!tail.has_comments());
+    assert_eq!(
+        unalz_rs::archive::TailInfo::parse(archive.reader.tail()),
+        tail
+    );
+}
+
+#[test]
+fn names_and_contains() {
+    let alz = build_alz(&[
+        local_file(b"Dir\\", 0x10, 0, 0, 0, b""),
+        store_file("Dir\\Read Me.txt", b"hi"),
+        store_file("b.txt", b"b"),
+    ]);
+    let archive = AlzArchive::from_bytes(alz).unwrap();
+    assert_eq!(
+        archive.names().collect::<Vec<_>>(),
+        ["Dir\\", "Dir\\Read Me.txt", "b.txt"]
+    );
+    assert!(archive.contains("Dir/Read Me.txt"));
+    assert!(archive.contains("Dir\\Read Me.txt"));
+    assert!(archive.contains("b.txt"));
+    assert!(!archive.contains("dir/read me.txt"));
+    assert!(archive.contains_ignore_case("dir/read me.txt"));
+    assert!(archive.contains_ignore_case("B.TXT"));
+    assert!(!archive.contains_ignore_case("c.txt"));
+}
+
+#[test]
+fn directory_size_ignored() {
+    // A directory header claiming 100 bytes of data that are not there.
+    let mut dir = local_file(b"dir/", 0x10, 0, 0, 100, b"");
+    dir[19..23].copy_from_slice(&100u32.to_le_bytes());
+    let alz = build_alz(&[dir, store_file("dir/a.txt", b"hello")]);
+    let mut archive = AlzArchive::from_bytes(alz).unwrap();
+    assert_eq!(archive.entries.len(), 2);
+    assert_eq!(archive.entries[0].uncompressed_size, 0);
+    assert_eq!(archive.entries[0].compressed_size, 0);
+    assert!(archive.validate_structure().is_empty());
+
+    let mut sink = MemorySink::new();
+    unalz_rs::extract::extract_all_to(&mut archive, &mut sink, &quiet()).unwrap();
+    assert!(sink.dirs.contains(Path::new("dir/")));
+    assert_eq!(sink.files[Path::new("dir/a.txt")], b"hello");
+}
+
+#[test]
+fn validate_structure_reports_issues() {
+    use unalz_rs::archive::StructuralIssue;
+
+    let archive = AlzArchive::from_bytes(build_alz(&[store_file("a.txt", b"hello")])).unwrap();
+    assert!(archive.end_record_seen);
+    assert!(archive.validate_structure().is_empty());
+
+    let alz = build_alz(&[local_file(b"b.txt", 0x20, 0, crc32(b"hello"), 9, b"hello")]);
+    let archive = AlzArchive::from_bytes(alz).unwrap();
+    assert_eq!(
+        archive.validate_structure(),
+        [StructuralIssue::StoreSizeMismatch {
+            index: 0,
+            offset: archive.entries[0].data_pos,
+            compressed: 5,
+            uncompressed: 9,
+        }]
+    );
+
+    // Cut off the end record and the last three data bytes.
+    let mut alz = build_alz(&[store_file("c.txt", b"hello world")]);
+    alz.truncate(alz.len() - 7);
+    let len = alz.len() as u64;
+    let archive = AlzArchive::from_bytes(alz).unwrap();
+    let offset = archive.entries[0].data_pos;
+    assert_eq!(
+        archive.validate_structure(),
+        [
+            StructuralIssue::DataOutOfBounds {
+                index: 0,
+                offset,
+                end: offset + 11,
+                available: len,
+            },
+            StructuralIssue::MissingEndRecord { offset: len },
+        ]
+    );
+}
+
+#[test]
+fn missing_end_record_is_an_error_when_required() {
+    use unalz_rs::archive::ParseOptions;
+
+    // T_ALZ's central directory record is cut short and swallows the end
+    // record, so cut before both of them.
+    let end = T_ALZ.windows(4).position(|w| w == b"CLZ\x01").unwrap();
+    let alz = T_ALZ[..end].to_vec();
+    let archive = AlzArchive::from_bytes(alz.clone()).unwrap();
+    assert!(!archive.end_record_seen);
+    assert_eq!(archive.entries.len(), 1);
+
+    let opts = ParseOptions {
+        require_end_record: true,
+        ..Default::default()
+    };
+    let reader = MultiVolumeReader::from_bytes(alz);
+    let err = AlzArchive::from_reader_with(reader, &opts).err().unwrap();
+    assert!(matches!(err, AlzError::MissingEndRecord), "{err}");
+
+    let reader = MultiVolumeReader::from_bytes(build_alz(&[store_file("a.txt", b"a")]));
+    assert!(AlzArchive::from_reader_with(reader, &opts).is_ok());
+}
+
+#[test]
+fn sfx_stub_skipped_when_scanning() {
+    use unalz_rs::archive::ParseOptions;
+
+    // A program stub that mentions the signature without a record after it.
+    let mut sfx = b"MZ\x90\x00".to_vec();
+    sfx.resize(1000, 0);
+    sfx.extend_from_slice(b"ALZ\x01 is not here");
+    sfx.resize(3000, 0xCC);
+    sfx.extend_from_slice(T_ALZ);
+
+    let err = AlzArchive::from_bytes(sfx.clone()).err().unwrap();
+    assert!(matches!(err, AlzError::NotAlzFile), "{err}");
+
+    let opts = ParseOptions {
+        sfx_scan_limit: Some(4096),
+        ..Default::default()
+    };
+    let reader = MultiVolumeReader::from_bytes(sfx.clone());
+    let mut archive = AlzArchive::from_reader_with(reader, &opts).unwrap();
+    let plain = AlzArchive::from_bytes(T_ALZ.to_vec()).unwrap();
+    assert_eq!(archive.entries[0].data_pos, plain.entries[0].data_pos);
+    assert_eq!(archive.read_at(0, 4).unwrap(), b"ALZ\x01");
+    let files = unalz_rs::extract::extract_to_map(&mut archive, None).unwrap();
+    assert_eq!(files["t/t.txt"], b"42");
+
+    let opts = ParseOptions {
+        sfx_scan_limit: Some(1024),
+        ..Default::default()
+    };
+    let reader = MultiVolumeReader::from_bytes(sfx);
+    assert!(
+        AlzArchive::from_reader_with(reader, &opts).is_err(),
+        "past the limit"
+    );
+}
+
+#[test]
+fn truncated_entry_reports_missing_volume() {
+    let data = vec![b'z'; 4000];
+    let mut alz = build_alz(&[local_file(
+        b"z.txt",
+        0x20,
+        2,
+        crc32(&data),
+        data.len() as u32,
+        &deflate(&data),
+    )]);
+    alz.truncate(alz.len() - 6);
+    let mut archive = AlzArchive::from_bytes(alz).unwrap();
+    let mut sink = MemorySink::new();
+    let err = unalz_rs::extract::extract_all_to(&mut archive, &mut sink, &quiet()).unwrap_err();
+    assert!(matches!(err, AlzError::MissingVolume(ref name) if name == "z.txt"));
+    assert!(sink.files.is_empty());
+}
+
+#[test]
+fn size_mismatch_detected() {
+    // Right CRC, wrong declared size: a truncated deflate stream that still
+    // passed the CRC check would look like this.
+    let compressed = deflate(b"short");
+    let alz = build_alz(&[local_file(
+        b"s.txt",
+        0x20,
+        2,
+        crc32(b"short"),
+        9,
+        &compressed,
+    )]);
+    let mut archive = AlzArchive::from_bytes(alz).unwrap();
+    let mut sink = MemorySink::new();
+    let err = unalz_rs::extract::extract_all_to(&mut archive, &mut sink, &quiet()).unwrap_err();
+    assert!(matches!(
+        err,
+        AlzError::SizeMismatch {
+            expected: 9,
+            got: 5
+        }
+    ));
+    assert!(sink.files.is_empty());
+}
+
+/// A header without size fields (descriptor 0x00) followed by data anyway,
+/// as a writer that defers sizes to a data descriptor would produce.
+fn sizeless_file(name: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut rec = b"BLZ\x01".to_vec();
+    rec.extend_from_slice(&(name.len() as u16).to_le_bytes());
+    rec.push(0x20);
+    rec.extend_from_slice(&0x4E8C2209u32.to_le_bytes());
+    rec.push(0x00);
+    rec.push(0);
+    rec.extend_from_slice(name);
+    rec.extend_from_slice(data);
+    rec
+}
+
+#[test]
+fn sizeless_header_with_data() {
+    use unalz_rs::archive::UnsupportedFeature;
+
+    let alz = build_alz(&[
+        sizeless_file(b"empty.txt", b""),
+        sizeless_file(b"deferred.bin", b"0123456789"),
+        store_file("after.txt", b"ok"),
+    ]);
+    let mut archive = AlzArchive::from_bytes(alz.clone()).unwrap();
+    assert_eq!(archive.entries.len(), 3);
+    assert!(!archive.entries[0].size_unknown);
+    // The empty entry's data ends where the next header starts.
+    assert_eq!(
+        &alz[archive.entries[0].data_pos as usize..][..4],
+        b"BLZ\x01"
+    );
+    let entry = &archive.entries[1];
+    assert!(entry.size_unknown);
+    assert_eq!(entry.compressed_size, 10);
+    assert_eq!(&alz[entry.data_pos as usize..][..10], b"0123456789");
+    assert_eq!(
+        archive.unsupported_features(),
+        [UnsupportedFeature::SizeUnknown {
+            file_name: "deferred.bin".into()
+        }]
+    );
+
+    let opts = ExtractOptions {
+        keep_going: true,
+        ..quiet()
+    };
+    let mut sink = MemorySink::new();
+    let err = unalz_rs::extract::extract_all_to(&mut archive, &mut sink, &opts).unwrap_err();
+    assert!(matches!(err, AlzError::SizeUnknown(ref n) if n == "deferred.bin"));
+    assert_eq!(sink.files[Path::new("empty.txt")], b"");
+    assert_eq!(sink.files[Path::new("after.txt")], b"ok");
+
+    let mut sink = MemorySink::new();
+    let err = AlzArchive::extract_streaming(ForwardOnly(&alz), &mut sink, &opts).unwrap_err();
+    assert!(matches!(err, AlzError::SizeUnknown(_)));
+    assert_eq!(sink.files[Path::new("after.txt")], b"ok");
+}
+
+#[test]
+fn keep_corrupt_and_keep_going() {
+    let alz = build_alz(&[
+        local_file(b"bad.txt", 0x20, 0, 0x1234, 5, b"hello"),
+        store_file("good.txt", b"fine"),
+    ]);
+    let mut archive = AlzArchive::from_bytes(alz).unwrap();
+    let mut sink = MemorySink::new();
+    let opts = ExtractOptions {
+        keep_corrupt: true,
+        keep_going: true,
+        ..quiet()
+    };
+    let err = unalz_rs::extract::extract_all_to(&mut archive, &mut sink, &opts).unwrap_err();
+    assert!(matches!(err, AlzError::InvalidFileCrc { .. }));
+    assert_eq!(sink.files[Path::new("bad.txt.corrupt")], b"hello");
+    assert_eq!(sink.files[Path::new("good.txt")], b"fine");
+    assert!(!sink.files.contains_key(Path::new("bad.txt")));
+
+    // On disk too.
+    let dir = test_dir().join("keep_corrupt");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let mut sink = unalz_rs::sink::RealFsSink::new(&dir);
+    assert!(unalz_rs::extract::extract_all_to(&mut archive, &mut sink, &opts).is_err());
+    assert_eq!(
+        std::fs::read(dir.join("bad.txt.corrupt")).unwrap(),
+        b"hello"
+    );
+    assert!(!dir.join("bad.txt").exists());
+    assert!(dir.join("good.txt").exists());
+}
+
+#[test]
+fn ignore_crc_keeps_mismatched_files() {
+    let alz = build_alz(&[
+        local_file(b"bad.txt", 0x20, 0, 0x1234, 5, b"hello"),
+        local_file(b"short.txt", 0x20, 0, crc32(b"hi"), 9, b"hi"),
+    ]);
+    let mut archive = AlzArchive::from_bytes(alz).unwrap();
+    let mut sink = MemorySink::new();
+    let opts = ExtractOptions {
+        ignore_crc: true,
+        keep_going: true,
+        ..quiet()
+    };
+    let err = unalz_rs::extract::extract_all_to(&mut archive, &mut sink, &opts).unwrap_err();
+    // Size mismatches are still errors.
+    assert!(matches!(err, AlzError::SizeMismatch { .. }));
+    assert_eq!(sink.files[Path::new("bad.txt")], b"hello");
+    assert!(!sink.files.contains_key(Path::new("short.txt")));
+
+    let (reader, entries) = archive.reader_and_entries();
+    let outcome =
+        unalz_rs::extract::extract_entry_to(reader, &entries[0], &mut sink, &opts).unwrap();
+    assert_eq!(outcome.crc, crc32(b"hello"));
+    assert_ne!(outcome.crc, entries[0].file_crc);
+}
+
+#[test]
+fn mtime_failures_reported_or_strict() {
+    use std::io::Write;
+    use std::time::SystemTime;
+    use unalz_rs::error::AlzResult;
+    use unalz_rs::sink::ExtractSink;
+
+    /// A sink on a filesystem without modification times.
+    struct NoTimes(MemorySink);
+
+    impl ExtractSink for NoTimes {
+        fn create_dir(&mut self, path: &Path) -> AlzResult<()> {
+            self.0.create_dir(path)
+        }
+        fn create_file(&mut self, path: &Path) -> AlzResult<Box<dyn Write + '_>> {
+            self.0.create_file(path)
+        }
+        fn symlink(&mut self, target: &str, path: &Path) -> AlzResult<()> {
+            self.0.symlink(target, path)
+        }
+        fn set_mtime(&mut self, _path: &Path, _time: SystemTime) -> AlzResult<()> {
+            Err(std::io::Error::from(std::io::ErrorKind::Unsupported).into())
+        }
+        fn remove_file(&mut self, path: &Path) -> AlzResult<()> {
+            self.0.remove_file(path)
+        }
+        fn rename(&mut self, from: &Path, to: &Path) -> AlzResult<()> {
+            self.0.rename(from, to)
+        }
+    }
+
+    let mut archive = AlzArchive::from_bytes(build_alz(&[store_file("a.txt", b"abc")])).unwrap();
+    let (reader, entries) = archive.reader_and_entries();
+    let mut sink = NoTimes(MemorySink::new());
+    let outcome =
+        unalz_rs::extract::extract_entry_to(reader, &entries[0], &mut sink, &quiet()).unwrap();
+    assert!(outcome.mtime_failed);
+    assert_eq!(sink.0.files[Path::new("a.txt")], b"abc");
+
+    let strict = ExtractOptions {
+        strict_times: true,
+        ..quiet()
+    };
+    let err = unalz_rs::extract::extract_all_to(&mut archive, &mut sink, &strict).unwrap_err();
+    assert!(matches!(err, AlzError::Io(_)));
+
+    let mut sink = MemorySink::new();
+    let summary = unalz_rs::extract::extract_all_to(&mut archive, &mut sink, &strict).unwrap();
+    assert_eq!(summary.entries, 1);
+}
+
+#[test]
+fn writes_to_sink_are_batched() {
+    use std::io::Write;
+    use std::sync::Arc;
+    use std::sync::atomic::AtomicUsize;
+    use unalz_rs::error::AlzResult;
+    use unalz_rs::sink::ExtractSink;
+
+    /// Counts write calls reaching the sink.
+    struct Counting(Arc<AtomicUsize>);
+
+    impl Write for Counting {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    struct CountingSink(Arc<AtomicUsize>);
+
+    impl ExtractSink for CountingSink {
+        fn create_dir(&mut self, _path: &Path) -> AlzResult<()> {
+            Ok(())
+        }
+        fn create_file(&mut self, _path: &Path) -> AlzResult<Box<dyn Write + '_>> {
+            Ok(Box::new(Counting(Arc::clone(&self.0))))
+        }
+        fn symlink(&mut self, _target: &str, _path: &Path) -> AlzResult<()> {
+            Ok(())
+        }
+        fn remove_file(&mut self, _path: &Path) -> AlzResult<()> {
+            Ok(())
+        }
+        fn rename(&mut self, _from: &Path, _to: &Path) -> AlzResult<()> {
+            Ok(())
+        }
+    }
+
+    // The content filter holds back the first bytes and writes them
+    // separately; both parts reach the sink as one write.
+    let mut archive =
+        AlzArchive::from_bytes(build_alz(&[store_file("a.bin", &[1; 1000])])).unwrap();
+    let writes = Arc::new(AtomicUsize::new(0));
+    let accept = |_: &[u8]| true;
+    let opts = ExtractOptions {
+        content_filter: Some(&accept),
+        ..quiet()
+    };
+    let mut sink = CountingSink(Arc::clone(&writes));
+    unalz_rs::extract::extract_all_to(&mut archive, &mut sink, &opts).unwrap();
+    assert_eq!(writes.load(Ordering::Relaxed), 1);
+}
+
+#[test]
+fn on_entry_order_is_archive_order() {
+    use std::sync::Mutex;
+
+    let alz = build_alz(&[
+        store_file("c.txt", b"3"),
+        local_file(b"bad.txt", 0x20, 0, 0x1234, 5, b"hello"),
+        store_file("a.png", b"\x89PNG\r\n\x1a\n"),
+        local_file(b"odd.bin", 0x20, 9, 0, 1, b"x"),
+        store_file("b.txt", b"2"),
+    ]);
+    let mut archive = AlzArchive::from_bytes(alz).unwrap();
+    let expected: Vec<_> = archive
+        .entries
+        .iter()
+        .map(|e| e.file_name.clone())
+        .collect();
+    for _ in 0..2 {
+        let seen = Mutex::new(Vec::new());
+        let on_entry = |entry: &unalz_rs::archive::AlzFileEntry, _: &_| {
+            seen.lock().unwrap().push(entry.file_name.clone());
+        };
+        let opts = ExtractOptions {
+            on_entry: Some(&on_entry),
+            content_filter: Some(&unalz_rs::extract::is_image),
+            keep_going: true,
+            ..quiet()
+        };
+        let mut sink = MemorySink::new();
+        let _ = unalz_rs::extract::extract_all_to(&mut archive, &mut sink, &opts);
+        assert_eq!(seen.into_inner().unwrap(), expected);
+    }
+}
+
+#[test]
+fn size_limits_skip_files() {
+    let alz = build_alz(&[
+        local_file(b"dir/", 0x10, 0, 0, 0, b""),
+        store_file("dir/small.txt", b"ab"),
+        store_file("dir/mid.txt", b"abcde"),
+        store_file("dir/big.txt", &[0; 100]),
+    ]);
+    let mut archive = AlzArchive::from_bytes(alz).unwrap();
+    let opts = ExtractOptions {
+        min_size: Some(3),
+        max_size: Some(99),
+        ..quiet()
+    };
+    let mut sink = MemorySink::new();
+    let summary = unalz_rs::extract::extract_all_to(&mut archive, &mut sink, &opts).unwrap();
+    assert_eq!(summary.entries, 2, "mid.txt and the directory");
+    assert!(sink.dirs.contains(Path::new("dir")));
+    assert_eq!(
+        sink.files.keys().collect::<Vec<_>>(),
+        [Path::new("dir/mid.txt")]
+    );
+    assert!(opts.size_selected(3) && opts.size_selected(99));
+    assert!(!opts.size_selected(2) && !opts.size_selected(100));
+}
+
+#[test]
+fn limit_stops_after_n_files() {
+    let alz = build_alz(&[
+        local_file(b"dir/", 0x10, 0, 0, 0, b""),
+        store_file("dir/a.txt", b"a"),
+        store_file("dir/b.txt", b"b"),
+        store_file("dir/c.txt", b"c"),
+    ]);
+    let opts = ExtractOptions {
+        limit: Some(2),
+        ..quiet()
+    };
+    let mut archive = AlzArchive::from_bytes(alz.clone()).unwrap();
+    let mut sink = MemorySink::new();
+    let summary = unalz_rs::extract::extract_all_to(&mut archive, &mut sink, &opts).unwrap();
+    assert!(summary.limited);
+    assert_eq!(summary.entries, 3, "the directory doesn't count");
+    assert_eq!(
+        sink.files.keys().collect::<Vec<_>>(),
+        [Path::new("dir/a.txt"), Path::new("dir/b.txt")]
+    );
+
+    let mut sink = MemorySink::new();
+    let summary = unalz_rs::extract::extract_streaming(&alz[..], &mut sink, &opts).unwrap();
+    assert!(summary.limited);
+    assert_eq!(sink.files.len(), 2);
+
+    let opts = ExtractOptions {
+        limit: Some(3),
+        ..quiet()
+    };
+    let summary =
+        unalz_rs::extract::extract_all_to(&mut archive, &mut MemorySink::new(), &opts).unwrap();
+    assert!(!summary.limited, "nothing was left over");
+}
+
+#[test]
+fn extract_to_map_resolves_symlinks() {
+    let alz = build_alz(&[
+        local_file(b"dir/", 0x10, 0, 0, 0, b""),
+        store_file("dir/a.txt", b"hello"),
+        local_file(b"dir/link", 0x40, 0, crc32(b"a.txt"), 5, b"a.txt"),
+        local_file(b"up", 0x40, 0, crc32(b"dir/link"), 8, b"dir/link"),
+        local_file(b"dangling", 0x40, 0, crc32(b"../x"), 4, b"../x"),
+    ]);
+    let mut archive = AlzArchive::from_bytes(alz).unwrap();
+    let map = unalz_rs::extract::extract_to_map(&mut archive, None).unwrap();
+    assert_eq!(
+        map.keys().map(String::as_str).collect::<Vec<_>>(),
+        ["dir/a.txt", "dir/link", "up"]
+    );
+    assert!(map.values().all(|data| data == b"hello"));
+}
+
+#[test]
+fn extract_to_map_enforces_size_limits() {
+    use unalz_rs::extract::{extract_to_map, extract_to_map_with};
+
+    // Declares 10 bytes but inflates to 1 MiB.
+    let bomb = vec![0u8; 1 << 20];
+    let alz = build_alz(&[local_file(
+        b"bomb.bin",
+        0x20,
+        2,
+        crc32(&bomb),
+        10,
+        &deflate(&bomb),
+    )]);
+    let mut archive = AlzArchive::from_bytes(alz).unwrap();
+    let err = extract_to_map(&mut archive, None).unwrap_err();
+    assert!(
+        matches!(&err, AlzError::SizeLimitExceeded { name, limit: 10 } if name == "bomb.bin"),
+        "{err}"
+    );
+
+    // The same for a symlink, whose target is decompressed in memory.
+    let alz = build_alz(&[local_file(
+        b"link",
+        0x40,
+        2,
+        crc32(&bomb),
+        10,
+        &deflate(&bomb),
+    )]);
+    let mut archive = AlzArchive::from_bytes(alz).unwrap();
+    let err = extract_to_map(&mut archive, None).unwrap_err();
+    assert!(matches!(err, AlzError::SizeLimitExceeded { limit: 10, .. }));
+
+    // The total across files, and symlink copies, counts too.
+    let alz = build_alz(&[
+        store_file("a.txt", b"hello"),
+        store_file("b.txt", b"world"),
+        local_file(b"link", 0x40, 0, crc32(b"a.txt"), 5, b"a.txt"),
+    ]);
+    let mut archive = AlzArchive::from_bytes(alz).unwrap();
+    let err = extract_to_map_with(&mut archive, None, 8).unwrap_err();
+    assert!(
+        matches!(&err, AlzError::SizeLimitExceeded { name, limit: 8 } if name == "b.txt"),
+        "{err}"
+    );
+    let err = extract_to_map_with(&mut archive, None, 12).unwrap_err();
+    assert!(
+        matches!(&err, AlzError::SizeLimitExceeded { name, limit: 12 } if name == "link"),
+        "{err}"
+    );
+    assert_eq!(
+        extract_to_map_with(&mut archive, None, 15).unwrap().len(),
+        3
+    );
+}
+
+#[test]
+fn report_collects_every_outcome_and_error() {
+    let alz = build_alz(&[
+        store_file("a.txt", b"a"),
+        local_file(b"bad1.txt", 0x20, 0, 0x1234, 3, b"bad"),
+        local_file(b"odd.bin", 0x20, 7, 0, 3, b"???"),
+        local_file(b"bad2.txt", 0x20, 0, 0x1234, 3, b"bad"),
+        store_file("b.txt", b"bb"),
+    ]);
+    let mut archive = AlzArchive::from_bytes(alz).unwrap();
+    let opts = ExtractOptions {
+        keep_going: true,
+        ..quiet()
+    };
+    let report =
+        unalz_rs::extract::extract_all_report(&mut archive, &mut MemorySink::new(), &opts).unwrap();
+    assert_eq!(report.summary.entries, 2);
+    assert_eq!(report.summary.bytes, 3);
+    let outcomes: Vec<_> = report
+        .per_entry
+        .iter()
+        .map(|(name, outcome)| (name.as_str(), outcome.skipped))
+        .collect();
+    assert_eq!(
+        outcomes,
+        [("a.txt", false), ("odd.bin", true), ("b.txt", false)]
+    );
+    let errors: Vec<_> = report
+        .errors
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect();
+    assert_eq!(errors, ["bad1.txt", "bad2.txt"]);
+    assert!(matches!(
+        report.errors[0].1,
+        AlzError::InvalidFileCrc { .. }
+    ));
+
+    // The summary-only form still fails with the first error.
+    let err =
+        unalz_rs::extract::extract_all_to(&mut archive, &mut MemorySink::new(), &opts).unwrap_err();
+    assert!(matches!(err, AlzError::InvalidFileCrc { got, .. } if got == crc32(b"bad")));
+}
+
+#[test]
+fn zero_crc_store_entry_not_checked() {
+    let alz = build_alz(&[
+        local_file(b"nocrc.txt", 0x20, 0, 0, 5, b"hello"),
+        local_file(b"short.txt", 0x20, 0, 0, 9, b"hello"),
+    ]);
+    let archive = AlzArchive::from_bytes(alz.clone()).unwrap();
+    assert!(!archive.entries[0].has_crc());
+    assert!(archive.entries[0].verify_data(&b"hello"[..]).unwrap());
+    assert!(!archive.entries[0].verify_data(&b"hell"[..]).unwrap());
+
+    let mut archive = AlzArchive::from_bytes(alz).unwrap();
+    let mut sink = MemorySink::new();
+    let opts = ExtractOptions {
+        keep_going: true,
+        ..quiet()
+    };
+    let err = unalz_rs::extract::extract_all_to(&mut archive, &mut sink, &opts).unwrap_err();
+    assert!(
+        matches!(err, AlzError::SizeMismatch { .. }),
+        "sizes still count"
+    );
+    assert_eq!(sink.files[Path::new("nocrc.txt")], b"hello");
+
+    // A compressed entry's zero CRC is still checked.
+    let compressed = deflate(b"hello");
+    let alz = build_alz(&[local_file(b"d.txt", 0x20, 2, 0, 5, &compressed)]);
+    let mut archive = AlzArchive::from_bytes(alz).unwrap();
+    assert!(archive.entries[0].has_crc());
+    let err = unalz_rs::extract::extract_all_to(&mut archive, &mut sink, &quiet()).unwrap_err();
+    assert!(matches!(err, AlzError::InvalidFileCrc { .. }));
+}
+
 #[test]
 fn extract_selective() {
     let (mut archive, dir) = open_test_archive();
@@ -67,6 +1500,67 @@ fn extract_selective() {
     assert_eq!(std::fs::read(out.join("t/t.txt")).unwrap(), b"42");
 }
 
+#[test]
+fn extract_by_index_with_duplicate_names() {
+    let alz = build_alz(&[
+        store_file("dup.txt", b"first"),
+        store_file("dup.txt", b"second"),
+    ]);
+    let mut archive = AlzArchive::from_bytes(alz).unwrap();
+    let mut sink = MemorySink::new();
+    let outcome =
+        unalz_rs::extract::extract_index_to(&mut archive, 1, &mut sink, &quiet()).unwrap();
+    assert_eq!(outcome.size, 6);
+    assert_eq!(sink.files[Path::new("dup.txt")], b"second");
+
+    let err =
+        unalz_rs::extract::extract_index_to(&mut archive, 2, &mut sink, &quiet()).unwrap_err();
+    assert!(matches!(err, AlzError::NoSuchEntry(2)));
+}
+
+#[test]
+fn latest_only_extracts_last_duplicate() {
+    let alz = build_alz(&[
+        store_file("dup.txt", b"first"),
+        store_file("other.txt", b"other"),
+        store_file("dup.txt", b"second"),
+    ]);
+    let mut archive = AlzArchive::from_bytes(alz).unwrap();
+    let opts = ExtractOptions {
+        latest_only: true,
+        ..quiet()
+    };
+    let mut sink = MemorySink::new();
+    let summary = unalz_rs::extract::extract_all_to(&mut archive, &mut sink, &opts).unwrap();
+    assert_eq!(summary.entries, 2);
+    assert_eq!(sink.files[Path::new("dup.txt")], b"second");
+
+    let mut sink = MemorySink::new();
+    let names = ["dup.txt".to_string()];
+    unalz_rs::extract::extract_files_to(&mut archive, &mut sink, &names, &opts).unwrap();
+    assert_eq!(sink.files[Path::new("dup.txt")], b"second");
+
+    let all: Vec<_> = archive.entries.iter().collect();
+    let latest = unalz_rs::extract::latest_only(&all);
+    assert_eq!(
+        latest.iter().map(|e| e.data_pos).collect::<Vec<_>>(),
+        [archive.entries[1].data_pos, archive.entries[2].data_pos]
+    );
+
+    // Named files follow the same rule: either slash makes a duplicate.
+    let alz = build_alz(&[
+        store_file("a/b.txt", b"first"),
+        store_file("a\\b.txt", b"second"),
+    ]);
+    let mut archive = AlzArchive::from_bytes(alz).unwrap();
+    for name in ["a/b.txt", "a\\b.txt"] {
+        let mut sink = MemorySink::new();
+        let names = [name.to_string()];
+        unalz_rs::extract::extract_files_to(&mut archive, &mut sink, &names, &opts).unwrap();
+        assert_eq!(sink.files[Path::new("a/b.txt")], b"second", "{name}");
+    }
+}
+
 #[test]
 fn extract_nonexistent_file_skipped() {
     let (mut archive, dir) = open_test_archive();
@@ -78,6 +1572,370 @@ fn extract_nonexistent_file_skipped() {
     assert!(!out.join("nonexistent.txt").exists());
 }
 
+#[test]
+fn extract_range_store() {
+    let data: Vec<u8> = (0..=255u8).cycle().take(100_000).collect();
+    let alz = build_alz(&[store_file("big.bin", &data)]);
+    let mut archive = AlzArchive::from_bytes(alz).unwrap();
+    let entry = archive.entries[0].clone();
+
+    let got = unalz_rs::extract::extract_range(&mut archive, &entry, None, 70_000, 1000).unwrap();
+    assert_eq!(got, &data[70_000..71_000]);
+
+    // Truncated at the end of the entry.
+    let got = unalz_rs::extract::extract_range(&mut archive, &entry, None, 99_990, 1000).unwrap();
+    assert_eq!(got, &data[99_990..]);
+    let got = unalz_rs::extract::extract_range(&mut archive, &entry, None, 200_000, 10).unwrap();
+    assert!(got.is_empty());
+}
+
+#[test]
+fn extract_range_deflate() {
+    let (mut archive, _dir) = open_test_archive();
+    let entry = archive.entries[0].clone();
+    let got = unalz_rs::extract::extract_range(&mut archive, &entry, None, 1, 10).unwrap();
+    assert_eq!(got, b"2");
+
+    // A window well before the end stops decompression early.
+    let data: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+    let compressed = deflate(&data);
+    let rec = local_file(
+        b"big.bin",
+        0x20,
+        2,
+        crc32(&data),
+        data.len() as u32,
+        &compressed,
+    );
+    let mut archive = AlzArchive::from_bytes(build_alz(&[rec])).unwrap();
+    let entry = archive.entries[0].clone();
+    let got = unalz_rs::extract::extract_range(&mut archive, &entry, None, 1000, 50).unwrap();
+    assert_eq!(got, &data[1000..1050]);
+}
+
+#[test]
+fn unsupported_features_reported() {
+    use unalz_rs::archive::UnsupportedFeature;
+
+    let (archive, _dir) = open_test_archive();
+    assert!(archive.unsupported_features().is_empty());
+
+    let mut odd = store_file("odd.bin", b"x");
+    odd[11] |= 0x04; // descriptor byte
+    let alz = build_alz(&[
+        local_file(b"ok.txt", 0x20, 0, crc32(b"ok"), 2, b"ok"),
+        local_file(b"lzma.bin", 0x20, 7, 0, 1, b"x"),
+        odd,
+    ]);
+    let archive = AlzArchive::from_bytes(alz).unwrap();
+    assert_eq!(
+        archive.unsupported_features(),
+        vec![
+            UnsupportedFeature::CompressionMethod {
+                file_name: "lzma.bin".into(),
+                method: 7,
+            },
+            UnsupportedFeature::DescriptorFlags {
+                file_name: "odd.bin".into(),
+                flags: 0x04,
+            },
+        ]
+    );
+}
+
+#[test]
+fn unknown_method_skipped_or_kept_raw() {
+    let alz = build_alz(&[
+        local_file(b"lzma.bin", 0x20, 7, 0, 10, b"packed"),
+        store_file("ok.txt", b"ok"),
+    ]);
+    let mut archive = AlzArchive::from_bytes(alz).unwrap();
+
+    let mut sink = MemorySink::new();
+    let err = unalz_rs::extract::extract_all_to(&mut archive, &mut sink, &quiet()).unwrap_err();
+    assert!(matches!(err, AlzError::UnknownCompressionMethod(7)));
+
+    let opts = ExtractOptions {
+        keep_going: true,
+        ..quiet()
+    };
+    let mut sink = MemorySink::new();
+    let summary = unalz_rs::extract::extract_all_to(&mut archive, &mut sink, &opts).unwrap();
+    assert_eq!(summary.entries, 1);
+    assert_eq!(sink.files.keys().collect::<Vec<_>>(), [Path::new("ok.txt")]);
+
+    let opts = ExtractOptions {
+        keep_unknown_raw: true,
+        ..quiet()
+    };
+    let mut sink = MemorySink::new();
+    let summary = unalz_rs::extract::extract_all_to(&mut archive, &mut sink, &opts).unwrap();
+    assert_eq!(summary.entries, 2);
+    assert_eq!(sink.files[Path::new("lzma.bin.raw")], b"packed");
+    assert!(!sink.files.contains_key(Path::new("lzma.bin")));
+}
+
+#[test]
+fn split_marker_between_records() {
+    let mut split = store_file("a.txt", b"first");
+    split.extend_from_slice(b"CLZ\x03");
+    let alz = build_alz(&[split, store_file("b.txt", b"second")]);
+    let mut archive = AlzArchive::from_bytes(alz).unwrap();
+    assert_eq!(archive.split_markers, 1);
+    assert_eq!(archive.volume_count(), 1);
+    let names: Vec<_> = archive
+        .entries
+        .iter()
+        .map(|e| e.file_name.as_str())
+        .collect();
+    assert_eq!(names, ["a.txt", "b.txt"]);
+    let entry = archive.entries[1].clone();
+    let got = unalz_rs::extract::extract_range(&mut archive, &entry, None, 0, 100).unwrap();
+    assert_eq!(got, b"second");
+}
+
+#[test]
+fn multi_volume_split_across_files() {
+    let mut split = store_file("a.txt", b"first volume data");
+    split.extend_from_slice(b"CLZ\x03");
+    let alz = build_alz(&[split, store_file("b.txt", b"second")]);
+    let (head, rest) = alz.split_at(30);
+
+    let dir = test_dir().join("multi_volume");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    // First volume: data + 16-byte tail; last volume: 8-byte header + data.
+    let mut vol0 = head.to_vec();
+    vol0.extend_from_slice(&[0u8; 16]);
+    let mut vol1 = vec![0u8; 8];
+    vol1.extend_from_slice(rest);
+    std::fs::write(dir.join("split.alz"), vol0).unwrap();
+    std::fs::write(dir.join("split.a00"), vol1).unwrap();
+
+    // Pointing at a continuation volume opens from the .alz.
+    let mut archive = AlzArchive::open(dir.join("split.a00").to_str().unwrap()).unwrap();
+    assert_eq!(archive.volume_count(), 2);
+    assert!(archive.is_split());
+    assert_eq!(archive.entries.len(), 2);
+
+    let mut joined = Vec::new();
+    let n = archive.reader.reassemble(&mut joined).unwrap();
+    assert_eq!(n, alz.len() as u64);
+    assert_eq!(joined, alz);
+    std::fs::write(dir.join("joined.alz"), &joined).unwrap();
+    let archive = AlzArchive::open(dir.join("joined.alz").to_str().unwrap()).unwrap();
+    assert_eq!(archive.volume_count(), 1);
+    assert!(!archive.is_split());
+    assert_eq!(archive.entries.len(), 2);
+
+    let mut archive = AlzArchive::open(dir.join("split.alz").to_str().unwrap()).unwrap();
+    assert_eq!(archive.volume_count(), 2);
+    let stream = AlzArchive::open_streaming(dir.join("split.alz").to_str().unwrap()).unwrap();
+    assert_eq!(stream.volume_count(), 2);
+
+    // a.txt's data starts at virtual offset 40, past the 30 bytes of
+    // volume 0; the split point falls inside its header.
+    assert_eq!(archive.entries[0].data_pos, 40);
+    assert_eq!(archive.entry_volumes(&archive.entries[0]), [1]);
+    let location = archive.physical_location(&archive.entries[0]);
+    assert_eq!(location, [(dir.join("split.a00"), 18, 17)]);
+    let vol1 = std::fs::read(&location[0].0).unwrap();
+    assert_eq!(&vol1[18..18 + 17], b"first volume data");
+    assert_eq!(archive.reader.volume_path(0), Some(&*dir.join("split.alz")));
+    assert!(
+        AlzArchive::from_bytes(alz.clone())
+            .unwrap()
+            .physical_location(&archive.entries[0])
+            .is_empty()
+    );
+    assert_eq!(
+        archive.reader.locate(20, 20),
+        [(0, 20, 10), (1, 8, 10)],
+        "spans the volume boundary"
+    );
+    assert_eq!(archive.reader.locate(25, 0), []);
+    assert_eq!(archive.read_at(20, 20).unwrap(), alz[20..40]);
+    let total = alz.len() as u64;
+    assert_eq!(archive.read_at(total - 4, 4).unwrap(), b"CLZ\x02");
+    assert!(archive.read_at(total - 4, 5).is_err());
+    assert!(archive.read_at(u64::MAX, 1).is_err());
+    assert_eq!(archive.reader.locate(0, u64::MAX).len(), 2);
+    assert_eq!(archive.split_markers, 1);
+    let entry = archive.entries[0].clone();
+    let got = unalz_rs::extract::extract_range(&mut archive, &entry, None, 0, 100).unwrap();
+    assert_eq!(got, b"first volume data");
+
+    // The same volumes supplied from memory, counting volume opens.
+    let vols = [
+        std::fs::read(dir.join("split.alz")).unwrap(),
+        std::fs::read(dir.join("split.a00")).unwrap(),
+    ];
+    let mut opened = 0;
+    let reader = MultiVolumeReader::open_with(|i: usize| {
+        opened += 1;
+        let vol = vols.get(i)?.clone();
+        Some(Box::new(Cursor::new(vol)) as Box<dyn ReadSeek>)
+    })
+    .unwrap();
+    assert_eq!(opened, 3);
+    assert_eq!(reader.total_size(), 30 + rest.len() as u64);
+    let mut archive = AlzArchive::from_reader(reader).unwrap();
+    assert_eq!(archive.volume_count(), 2);
+    let entry = archive.entries[1].clone();
+    let got = unalz_rs::extract::extract_range(&mut archive, &entry, None, 0, 100).unwrap();
+    assert_eq!(got, b"second");
+}
+
+#[test]
+fn try_clone_reads_independently() {
+    use std::io::{Seek, SeekFrom};
+
+    let mut split = store_file("a.txt", b"first volume data");
+    split.extend_from_slice(b"CLZ\x03");
+    let alz = build_alz(&[split, store_file("b.txt", b"second")]);
+    let (head, rest) = alz.split_at(30);
+    let dir = test_dir();
+    let mut vol0 = head.to_vec();
+    vol0.extend_from_slice(&[0u8; 16]);
+    let mut vol1 = vec![0u8; 8];
+    vol1.extend_from_slice(rest);
+    std::fs::write(dir.join("split.alz"), vol0).unwrap();
+    std::fs::write(dir.join("split.a00"), vol1).unwrap();
+
+    let from_files = AlzArchive::open(dir.join("split.alz").to_str().unwrap()).unwrap();
+    let from_memory = AlzArchive::from_bytes(alz.clone()).unwrap();
+    let from_slice = AlzArchive::from_slice(&alz).unwrap();
+    for mut archive in [from_files, from_memory, from_slice] {
+        let mut copy = archive.try_clone().unwrap();
+        assert_eq!(copy.volume_count(), archive.volume_count());
+        assert_eq!(copy.entries.len(), 2);
+        let entry = archive.entries[1].clone();
+        // Moving one cursor does not move the other.
+        archive.reader.seek(SeekFrom::Start(0)).unwrap();
+        let got = unalz_rs::extract::extract_range(&mut copy, &entry, None, 0, 100).unwrap();
+        assert_eq!(got, b"second");
+        let mut sink = MemorySink::new();
+        unalz_rs::extract::extract_all_to(&mut archive, &mut sink, &quiet()).unwrap();
+        assert_eq!(sink.files[Path::new("a.txt")], b"first volume data");
+    }
+
+    let reader = MultiVolumeReader::open_with(|i: usize| {
+        (i == 0).then(|| Box::new(Cursor::new(alz.clone())) as Box<dyn ReadSeek>)
+    })
+    .unwrap();
+    let err = AlzArchive::from_reader(reader)
+        .unwrap()
+        .try_clone()
+        .err()
+        .unwrap();
+    assert_eq!(err.kind(), std::io::ErrorKind::Unsupported);
+}
+
+#[test]
+fn unsupported_size_field_widths_rejected() {
+    for nibble in [0x30u8, 0x50, 0x60, 0xF0] {
+        let mut rec = store_file("a.txt", b"hello");
+        rec[11] = nibble;
+        let err = AlzArchive::from_bytes(build_alz(&[rec])).err().unwrap();
+        assert!(
+            matches!(err, AlzError::InvalidSizeFieldWidth(n) if n == nibble),
+            "{nibble:#x}: {err}"
+        );
+    }
+}
+
+#[test]
+fn huge_sizes_do_not_overflow() {
+    use std::io::{Read, Seek, SeekFrom};
+
+    let mut reader = MultiVolumeReader::from_bytes(vec![0; 10]);
+    reader.seek(SeekFrom::Start(u64::MAX)).unwrap();
+    assert!(reader.seek(SeekFrom::Current(i64::MAX)).is_err());
+    assert!(reader.seek(SeekFrom::End(-11)).is_err());
+    assert_eq!(reader.seek(SeekFrom::End(-10)).unwrap(), 0);
+
+    // A middle volume shorter than the 16-byte volume trailer.
+    let vols = [
+        build_alz(&[store_file("a.txt", b"hello")]),
+        vec![0; 4],
+        vec![0; 12],
+    ];
+    let mut reader = MultiVolumeReader::open_with(|i: usize| {
+        let vol = vols.get(i)?.clone();
+        Some(Box::new(Cursor::new(vol)) as Box<dyn ReadSeek>)
+    })
+    .unwrap();
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf).unwrap();
+
+    let alz = build_alz(&[store_file("a.txt", b"hello"), store_file("b.txt", b"hi")]);
+    let mut archive = AlzArchive::from_bytes(alz).unwrap();
+    archive.entries[0].uncompressed_size = u64::MAX;
+    archive.entries[1].uncompressed_size = u64::MAX;
+    let entry = archive.entries[0].clone();
+    let got = unalz_rs::extract::extract_range(&mut archive, &entry, None, u64::MAX - 1, 10);
+    assert!(matches!(got, Err(AlzError::CorruptedFile)));
+
+    let opts = ExtractOptions {
+        placeholders: true,
+        ..quiet()
+    };
+    let mut sink = MemorySink::new();
+    let summary = unalz_rs::extract::extract_all_to(&mut archive, &mut sink, &opts).unwrap();
+    assert_eq!(summary.bytes, u64::MAX);
+}
+
+#[test]
+#[allow(clippy::seek_from_current)] // Current(0) is what is under test
+fn multi_volume_relative_seeks() {
+    use std::io::{Read, Seek, SeekFrom};
+
+    // Virtual stream "0123456789abcdefghijKLMNO" over three volumes.
+    let mut vol0 = b"0123456789".to_vec();
+    vol0.extend_from_slice(&[0; 16]);
+    let mut vol1 = vec![0; 8];
+    vol1.extend_from_slice(b"abcdefghij");
+    vol1.extend_from_slice(&[0; 16]);
+    let mut vol2 = vec![0; 8];
+    vol2.extend_from_slice(b"KLMNO");
+    let vols = [vol0, vol1, vol2];
+    let mut reader = MultiVolumeReader::open_with(|i: usize| {
+        let vol = vols.get(i)?.clone();
+        Some(Box::new(Cursor::new(vol)) as Box<dyn ReadSeek>)
+    })
+    .unwrap();
+    let read = |reader: &mut MultiVolumeReader, n: usize| {
+        let mut buf = vec![0; n];
+        let got = reader.read(&mut buf).unwrap();
+        buf.truncate(got);
+        String::from_utf8(buf).unwrap()
+    };
+
+    reader.seek(SeekFrom::Start(5)).unwrap();
+    assert_eq!(read(&mut reader, 10), "56789abcde");
+    assert_eq!(reader.seek(SeekFrom::Current(-5)).unwrap(), 10);
+    assert_eq!(read(&mut reader, 3), "abc");
+    assert_eq!(reader.seek(SeekFrom::Current(0)).unwrap(), 13);
+    assert_eq!(read(&mut reader, 10), "defghijKLM");
+
+    // Stopping exactly at a boundary, then seeking by zero.
+    reader.seek(SeekFrom::Start(0)).unwrap();
+    assert_eq!(read(&mut reader, 10), "0123456789");
+    assert_eq!(reader.seek(SeekFrom::Current(0)).unwrap(), 10);
+    assert_eq!(read(&mut reader, 1), "a");
+    assert_eq!(reader.seek(SeekFrom::Current(-2)).unwrap(), 9);
+    assert_eq!(read(&mut reader, 2), "9a");
+
+    // Past the end and back.
+    reader.seek(SeekFrom::Start(20)).unwrap();
+    assert_eq!(read(&mut reader, 10), "KLMNO");
+    assert_eq!(read(&mut reader, 10), "");
+    assert_eq!(reader.seek(SeekFrom::Current(-10)).unwrap(), 15);
+    assert_eq!(read(&mut reader, 5), "fghij");
+    assert_eq!(reader.seek(SeekFrom::End(-1)).unwrap(), 24);
+    assert_eq!(read(&mut reader, 5), "O");
+}
+
 #[test]
 fn reject_non_alz() {
     let dir = test_dir();
@@ -96,6 +1954,73 @@ fn reject_path_traversal() {
     assert!(unalz_rs::extract::extract_all(&mut archive, &out, None, false, true).is_err());
 }
 
+#[test]
+fn extract_with_prefix() {
+    let (mut archive, _dir) = open_test_archive();
+    let mut sink = MemorySink::new();
+    let opts = ExtractOptions {
+        prefix: Some("ns/one"),
+        ..quiet()
+    };
+    unalz_rs::extract::extract_all_to(&mut archive, &mut sink, &opts).unwrap();
+    assert_eq!(sink.files[Path::new("ns/one/t/t.txt")], b"42");
+
+    let dir = test_dir().join("prefix");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let mut sink = unalz_rs::sink::RealFsSink::new(&dir);
+    unalz_rs::extract::extract_all_to(&mut archive, &mut sink, &opts).unwrap();
+    assert_eq!(std::fs::read(dir.join("ns/one/t/t.txt")).unwrap(), b"42");
+
+    for bad in ["../up", "/abs", "a/../../b"] {
+        let opts = ExtractOptions {
+            prefix: Some(bad),
+            ..quiet()
+        };
+        let err = unalz_rs::extract::extract_all_to(&mut archive, &mut sink, &opts).unwrap_err();
+        assert!(matches!(err, AlzError::PathTraversal(_)), "{bad}");
+    }
+}
+
+#[test]
+fn reject_deep_paths() {
+    let deep = "d/".repeat(300) + "f.txt";
+    let alz = build_alz(&[store_file(&deep, b"x"), store_file("a/b/c.txt", b"y")]);
+    let mut archive = AlzArchive::from_bytes(alz).unwrap();
+    let mut sink = MemorySink::new();
+    let err = unalz_rs::extract::extract_all_to(&mut archive, &mut sink, &quiet()).unwrap_err();
+    assert!(matches!(err, AlzError::PathTooDeep(ref p) if *p == deep));
+
+    let opts = ExtractOptions {
+        max_path_depth: 3,
+        keep_going: true,
+        ..quiet()
+    };
+    let mut sink = MemorySink::new();
+    assert!(unalz_rs::extract::extract_all_to(&mut archive, &mut sink, &opts).is_err());
+    assert_eq!(sink.files[Path::new("a/b/c.txt")], b"y");
+}
+
+#[test]
+fn reject_control_chars_in_name() {
+    let alz = build_alz(&[store_file("evil\0.txt", b"x"), store_file("ok.txt", b"y")]);
+    let mut archive = AlzArchive::from_bytes(alz).unwrap();
+    assert_eq!(archive.entries[0].file_name, "evil\0.txt");
+
+    let mut sink = MemorySink::new();
+    let err = unalz_rs::extract::extract_all_to(&mut archive, &mut sink, &quiet()).unwrap_err();
+    assert!(matches!(err, AlzError::UnsafeFileName(ref n) if n == "evil\0.txt"));
+    assert!(sink.files.is_empty());
+
+    let opts = ExtractOptions {
+        sanitize: true,
+        ..quiet()
+    };
+    unalz_rs::extract::extract_all_to(&mut archive, &mut sink, &opts).unwrap();
+    assert_eq!(sink.files[Path::new("evil%00.txt")], b"x");
+    assert_eq!(sink.files[Path::new("ok.txt")], b"y");
+}
+
 #[test]
 fn raw_decompress() {
     let data = b"hello world";
@@ -110,6 +2035,29 @@ fn raw_decompress() {
     assert_eq!(crc, h.finalize());
 }
 
+#[test]
+fn raw_size_beyond_u32_fails_cleanly() {
+    // On 32-bit targets a truncating cast would turn 1 << 32 into a zero-byte
+    // read and loop forever; the short input must surface as an error.
+    let mut reader = Cursor::new(b"abc".as_slice());
+    let mut output = Vec::new();
+    assert!(raw::extract_raw(&mut reader, &mut output, 1 << 32, None).is_err());
+
+    let mut reader = Cursor::new(b"abc".as_slice());
+    assert!(deflate::extract_deflate(&mut reader, &mut output, 1 << 32, None).is_err());
+}
+
+#[test]
+fn bzip2_size_beyond_limit_rejected() {
+    let mut reader = Cursor::new(b"abc".as_slice());
+    let mut output = Vec::new();
+    assert!(bzip2::extract_bzip2(&mut reader, &mut output, u64::MAX, None).is_err());
+
+    // Declared size larger than the available data.
+    let mut reader = Cursor::new(b"abc".as_slice());
+    assert!(bzip2::extract_bzip2(&mut reader, &mut output, 100, None).is_err());
+}
+
 #[test]
 fn deflate_decompress() {
     use flate2::Compression;
@@ -133,6 +2081,33 @@ fn deflate_decompress() {
     assert_eq!(crc, h.finalize());
 }
 
+#[test]
+fn deflate_tiny_buffers() {
+    // One-byte buffers make the decoder stall on input and output in turn.
+    let data: Vec<u8> = (0..3000u32).map(|i| (i * 7 % 256) as u8).collect();
+    let compressed = deflate(&data);
+    for buf_size in [1, 2, 3, 64] {
+        let mut output = Vec::new();
+        let crc = deflate::extract_deflate_buffered(
+            &mut compressed.as_slice(),
+            &mut output,
+            compressed.len() as u64,
+            None,
+            buf_size,
+        )
+        .unwrap();
+        assert_eq!(output, data, "buf_size {buf_size}");
+        assert_eq!(crc, crc32(&data));
+    }
+
+    // Truncated input ends without an error; the CRC no longer matches.
+    let half = &compressed[..compressed.len() / 2];
+    let mut output = Vec::new();
+    let crc =
+        deflate::extract_deflate(&mut &half[..], &mut output, half.len() as u64, None).unwrap();
+    assert_ne!(crc, crc32(&data));
+}
+
 #[test]
 fn bzip2_decompress() {
     // ALZ-format bzip2 data for "hello world" (generated from standard bzip2
@@ -149,7 +2124,249 @@ fn bzip2_decompress() {
     let crc = bzip2::extract_bzip2(&mut reader, &mut output, alz_bz2.len() as u64, None).unwrap();
 
     assert_eq!(&output, b"hello world");
+    assert_eq!(
+        bzip2::decompress_alz_block(alz_bz2).unwrap(),
+        b"hello world"
+    );
+    assert!(bzip2::decompress_alz_block(&alz_bz2[..20]).is_err());
     let mut h = crc32fast::Hasher::new();
     h.update(b"hello world");
     assert_eq!(crc, h.finalize());
 }
+
+#[test]
+fn reader_for_each_method() {
+    use std::io::Read;
+    use unalz_rs::decompress::reader_for;
+
+    // Store: reads stop at `size` even if the inner reader has more.
+    let mut out = Vec::new();
+    reader_for(CompressionMethod::Store, &b"abcdef"[..], 4, None)
+        .unwrap()
+        .read_to_end(&mut out)
+        .unwrap();
+    assert_eq!(out, b"abcd");
+
+    let compressed = deflate(b"hello deflate");
+    let mut out = Vec::new();
+    reader_for(
+        CompressionMethod::Deflate,
+        compressed.as_slice(),
+        compressed.len() as u64,
+        None,
+    )
+    .unwrap()
+    .read_to_end(&mut out)
+    .unwrap();
+    assert_eq!(out, b"hello deflate");
+
+    let alz_bz2: &[u8] = &[
+        0x44, 0x4c, 0x5a, 0x01, 0x00, 0x00, 0x03, 0x23, 0x00, 0x80, 0x00, 0x0c, 0x89, 0x21, 0x00,
+        0x40, 0x00, 0x44, 0x06, 0x69, 0x08, 0x60, 0x43, 0x6d, 0x02, 0xa8, 0x4f, 0x44, 0x4c, 0x5a,
+        0x02,
+    ];
+    let mut out = Vec::new();
+    reader_for(
+        CompressionMethod::Bzip2,
+        alz_bz2,
+        alz_bz2.len() as u64,
+        None,
+    )
+    .unwrap()
+    .read_to_end(&mut out)
+    .unwrap();
+    assert_eq!(out, b"hello world");
+
+    assert!(matches!(
+        reader_for(CompressionMethod::Unknown(9), &b""[..], 0, None),
+        Err(AlzError::UnknownCompressionMethod(9))
+    ));
+}
+
+#[test]
+fn entry_reader_composes() {
+    use std::io::Read;
+
+    let (mut archive, _dir) = open_test_archive();
+    let (reader, entries) = archive.reader_and_entries();
+    let mut r = unalz_rs::extract::entry_reader(reader, &entries[0], None).unwrap();
+    let mut out = String::new();
+    r.read_to_string(&mut out).unwrap();
+    assert_eq!(out, "42");
+}
+
+#[test]
+fn on_entry_sees_every_result() {
+    use std::sync::Mutex;
+    use unalz_rs::archive::AlzFileEntry;
+    use unalz_rs::error::AlzResult;
+    use unalz_rs::extract::EntryOutcome;
+
+    let alz = build_alz(&[store_file("a.txt", b"abc"), store_file("b\x01.txt", b"x")]);
+    let mut archive = AlzArchive::from_bytes(alz).unwrap();
+    let seen = Mutex::new(Vec::new());
+    let on_entry = |entry: &AlzFileEntry, result: &AlzResult<EntryOutcome>| {
+        let size = result.as_ref().ok().map(|o| o.size);
+        seen.lock().unwrap().push((entry.file_name.clone(), size));
+    };
+    let opts = ExtractOptions {
+        on_entry: Some(&on_entry),
+        keep_going: true,
+        ..quiet()
+    };
+    let mut sink = MemorySink::new();
+    unalz_rs::extract::extract_all_to(&mut archive, &mut sink, &opts).unwrap_err();
+    assert_eq!(
+        seen.into_inner().unwrap(),
+        [
+            ("a.txt".to_string(), Some(3)),
+            ("b\x01.txt".to_string(), None)
+        ]
+    );
+}
+
+#[test]
+fn mixed_encryption_needs_password_only_for_encrypted_entries() {
+    use unalz_rs::crypto::ZipCrypto;
+
+    let secret = b"top secret";
+    let mut crypto = ZipCrypto::new(b"pw");
+    let mut data = crypto
+        .make_header((crc32(secret) >> 24) as u8, *b"padding-11b")
+        .to_vec();
+    let mut body = secret.to_vec();
+    crypto.encrypt(&mut body);
+    data.extend_from_slice(&body);
+    let mut rec = local_file(
+        b"secret.txt",
+        0x20,
+        0,
+        crc32(secret),
+        secret.len() as u32,
+        &data,
+    );
+    rec[11] |= 0x01; // encrypted
+    // The compressed size does not count the encryption header.
+    rec[19..23].copy_from_slice(&(secret.len() as u32).to_le_bytes());
+    let alz = build_alz(&[rec, store_file("plain.txt", b"hello")]);
+
+    let mut archive = AlzArchive::from_bytes(alz).unwrap();
+    assert!(archive.is_encrypted);
+    assert!(archive.needs_password(None));
+    assert!(!archive.needs_password(Some(&["plain.txt".to_string()])));
+    assert!(archive.needs_password(Some(&["secret.txt".to_string()])));
+
+    let mut sink = MemorySink::new();
+    unalz_rs::extract::extract_files_to(
+        &mut archive,
+        &mut sink,
+        &["plain.txt".to_string()],
+        &quiet(),
+    )
+    .unwrap();
+    assert_eq!(sink.files[Path::new("plain.txt")], b"hello");
+
+    let mut sink = MemorySink::new();
+    let err = unalz_rs::extract::extract_all_to(&mut archive, &mut sink, &quiet()).unwrap_err();
+    assert!(matches!(err, AlzError::PasswordNotSet));
+
+    let opts = ExtractOptions {
+        password: Some("pw"),
+        ..quiet()
+    };
+    let mut sink = MemorySink::new();
+    unalz_rs::extract::extract_all_to(&mut archive, &mut sink, &opts).unwrap();
+    assert_eq!(sink.files[Path::new("secret.txt")], secret);
+    assert_eq!(sink.files[Path::new("plain.txt")], b"hello");
+}
+
+#[test]
+fn test_archive_checks_every_file() {
+    let compressed = deflate(b"deflated data");
+    let alz = build_alz(&[
+        local_file(b"dir", 0x10, 0, 0, 0, b""),
+        store_file("a.txt", b"hello"),
+        local_file(b"bad.txt", 0x20, 0, 0x1234_5678, 5, b"hello"),
+        local_file(b"d.txt", 0x20, 2, crc32(b"deflated data"), 13, &compressed),
+    ]);
+    let mut archive = AlzArchive::from_bytes(alz).unwrap();
+    let results = unalz_rs::extract::test_archive(&mut archive, None);
+    let names: Vec<_> = results.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(names, ["a.txt", "bad.txt", "d.txt"]);
+    assert!(results[0].1.is_ok());
+    assert!(matches!(results[1].1, Err(AlzError::InvalidFileCrc { .. })));
+    assert!(results[2].1.is_ok());
+
+    #[cfg(feature = "rayon")]
+    {
+        let parallel = unalz_rs::extract::test_archive_parallel(&archive, None).unwrap();
+        let summarize = |r: &[(String, unalz_rs::error::AlzResult<()>)]| {
+            r.iter()
+                .map(|(name, res)| (name.clone(), res.is_ok()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(summarize(&parallel), summarize(&results));
+    }
+}
+
+#[test]
+fn compressed_entry_without_data_is_a_size_mismatch() {
+    for method in [1, 2] {
+        let alz = build_alz(&[local_file(
+            b"empty.txt",
+            0x20,
+            method,
+            crc32(b"hello"),
+            5,
+            b"",
+        )]);
+        let mut archive = AlzArchive::from_bytes(alz).unwrap();
+        let mut sink = MemorySink::new();
+        let err = unalz_rs::extract::extract_all_to(&mut archive, &mut sink, &quiet()).unwrap_err();
+        assert!(
+            matches!(
+                err,
+                AlzError::SizeMismatch {
+                    expected: 5,
+                    got: 0
+                }
+            ),
+            "method {method}: {err}"
+        );
+    }
+}
+
+#[test]
+fn verify_against_dir_reports_discrepancies() {
+    use unalz_rs::extract::Discrepancy;
+
+    let alz = build_alz(&[
+        local_file(b"sub", 0x10, 0, 0, 0, b""),
+        store_file("sub/a.txt", b"hello"),
+        store_file("sub/b.txt", b"world"),
+        store_file("c.txt", b"abc"),
+        store_file("deep/d.txt", b"d"),
+    ]);
+    let mut archive = AlzArchive::from_bytes(alz).unwrap();
+    let dir = test_dir();
+    let mut sink = unalz_rs::sink::RealFsSink::new(&dir);
+    unalz_rs::extract::extract_all_to(&mut archive, &mut sink, &quiet()).unwrap();
+    assert!(unalz_rs::extract::verify_against_dir(&archive, &dir).is_empty());
+
+    std::fs::write(dir.join("sub/a.txt"), b"HELLO").unwrap();
+    std::fs::write(dir.join("sub/b.txt"), b"longer").unwrap();
+    std::fs::remove_file(dir.join("c.txt")).unwrap();
+    std::fs::write(dir.join("deep/extra.txt"), b"").unwrap();
+    std::fs::create_dir_all(dir.join("stray/inner")).unwrap();
+
+    let found = unalz_rs::extract::verify_against_dir(&archive, &dir);
+    assert_eq!(found.len(), 5, "{found:?}");
+    assert!(matches!(&found[0], Discrepancy::Missing(p) if p == Path::new("c.txt")));
+    assert!(matches!(&found[1], Discrepancy::CrcMismatch(p) if p == Path::new("sub/a.txt")));
+    assert!(matches!(
+        &found[2],
+        Discrepancy::SizeMismatch { path, expected: 5, got: 6 } if path == Path::new("sub/b.txt")
+    ));
+    assert!(matches!(&found[3], Discrepancy::Unexpected(p) if p == Path::new("deep/extra.txt")));
+    assert!(matches!(&found[4], Discrepancy::Unexpected(p) if p == Path::new("stray")));
+}